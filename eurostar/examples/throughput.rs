@@ -0,0 +1,54 @@
+// Compares the throughput of the channel flavors.
+// Run with: cargo run --release --example throughput
+use eurostar::{
+    block_channel, channel, list_channel, sync_channel, ChannelFlavor, Receiver, Sender,
+};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const MESSAGES: usize = 1_000_000;
+
+// Generic over the flavor: the same code runs on every backend
+fn run<F>(name: &str, (tx, mut rx): (Sender<usize, F>, Receiver<usize, F>), senders: usize)
+where
+    F: ChannelFlavor<usize> + Send + Sync + 'static,
+{
+    let per_sender = MESSAGES / senders;
+    let start = Instant::now();
+    let handles: Vec<_> = (0..senders)
+        .map(|_| {
+            let mut tx = tx.clone();
+            thread::spawn(move || {
+                for i in 0..per_sender {
+                    tx.send(i).unwrap();
+                }
+            })
+        })
+        .collect();
+    drop(tx);
+    let received = rx.iter().count();
+    let elapsed = start.elapsed();
+    for h in handles {
+        h.join().unwrap();
+    }
+    assert_eq!(received, per_sender * senders);
+    report(name, senders, received, elapsed);
+}
+
+fn report(name: &str, senders: usize, messages: usize, elapsed: Duration) {
+    let rate = messages as f64 / elapsed.as_secs_f64() / 1e6;
+    println!(
+        "{:<8} {:>2} senders: {:>8.2?} ({:.2} M msg/s)",
+        name, senders, elapsed, rate
+    );
+}
+
+fn main() {
+    // Contended: all the senders push to the same channel
+    for &senders in &[1, 4, 16] {
+        run("mutex", channel(), senders);
+        run("bounded", sync_channel(1024), senders);
+        run("list", list_channel(), senders);
+        run("block", block_channel(), senders);
+    }
+}
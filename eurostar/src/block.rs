@@ -0,0 +1,290 @@
+use crate::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+use crate::sync::thread;
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::ptr;
+
+// Atomic block linked list: linked list of fixed-size blocks of slots.
+// One allocation per BLOCK_CAP values, senders claim slots by bumping the tail index.
+
+// Number of slots in a block
+const BLOCK_CAP: usize = 31;
+// Indices go through one more step per block, used as a marker while the next block is installed
+const LAP: usize = BLOCK_CAP + 1;
+
+struct Slot<T> {
+    value: UnsafeCell<MaybeUninit<T>>,
+    // The sender that claimed the slot finished writing the value
+    ready: AtomicBool,
+}
+
+struct Block<T> {
+    next: AtomicPtr<Block<T>>,
+    slots: [Slot<T>; BLOCK_CAP],
+}
+
+impl<T> Block<T> {
+    fn new() -> Box<Self> {
+        Box::new(Block {
+            next: AtomicPtr::new(ptr::null_mut()),
+            slots: std::array::from_fn(|_| Slot {
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+                ready: AtomicBool::new(false),
+            }),
+        })
+    }
+}
+
+pub struct Queue<T> {
+    // Next slot to be claimed by a sender, and the block it belongs to
+    tail_index: AtomicUsize,
+    tail_block: AtomicPtr<Block<T>>,
+    // Next slot to be read by the consumer, and the block it belongs to
+    head_index: UnsafeCell<usize>,
+    head_block: UnsafeCell<*mut Block<T>>,
+}
+
+impl<T> crate::lockfree::Queue for Queue<T> {
+    type Item = T;
+
+    fn new() -> Self {
+        let block = Box::into_raw(Block::new());
+        Queue {
+            tail_index: AtomicUsize::new(0),
+            tail_block: AtomicPtr::new(block),
+            head_index: UnsafeCell::new(0),
+            head_block: UnsafeCell::new(block),
+        }
+    }
+
+    fn push(&self, t: T) {
+        let mut next_block = None;
+        let mut tail = self.tail_index.load(Ordering::Acquire);
+        let mut block = self.tail_block.load(Ordering::Acquire);
+        loop {
+            let offset = tail % LAP;
+            if offset == BLOCK_CAP {
+                // Another sender claimed the last slot and is installing the next block
+                thread::yield_now();
+                tail = self.tail_index.load(Ordering::Acquire);
+                block = self.tail_block.load(Ordering::Acquire);
+                continue;
+            }
+            // About to claim the last slot: allocate the next block beforehand
+            // so that the other senders do not wait on the allocation
+            if offset + 1 == BLOCK_CAP && next_block.is_none() {
+                next_block = Some(Block::new());
+            }
+            match self.tail_index.compare_exchange_weak(
+                tail,
+                tail + 1,
+                Ordering::SeqCst,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => {
+                    // SAFETY: the block cannot be freed by the consumer before the claimed slot is ready
+                    unsafe {
+                        if offset + 1 == BLOCK_CAP {
+                            // Install the next block and skip the marker index
+                            let next = Box::into_raw(next_block.take().unwrap());
+                            self.tail_block.store(next, Ordering::Release);
+                            self.tail_index.fetch_add(1, Ordering::Release);
+                            (*block).next.store(next, Ordering::Release);
+                        }
+                        let slot = &(*block).slots[offset];
+                        slot.value.get().write(MaybeUninit::new(t));
+                        slot.ready.store(true, Ordering::Release);
+                    }
+                    return;
+                }
+                Err(current) => {
+                    tail = current;
+                    block = self.tail_block.load(Ordering::Acquire);
+                }
+            }
+        }
+    }
+
+    unsafe fn pop(&self) -> Option<T> {
+        loop {
+            let head = *self.head_index.get();
+            let block = *self.head_block.get();
+            let offset = head % LAP;
+            if offset == BLOCK_CAP {
+                // End of the block: it was linked to the next one before its last slot was ready
+                let next = (*block).next.load(Ordering::Acquire);
+                *self.head_block.get() = next;
+                *self.head_index.get() = head + 1;
+                drop(Box::from_raw(block));
+                continue;
+            }
+            if head == self.tail_index.load(Ordering::Acquire) {
+                return None;
+            }
+            // The slot was claimed, wait for the sender to write the value
+            let slot = &(*block).slots[offset];
+            while !slot.ready.load(Ordering::Acquire) {
+                thread::yield_now();
+            }
+            *self.head_index.get() = head + 1;
+            return Some(slot.value.get().read().assume_init());
+        }
+    }
+
+    unsafe fn is_empty(&self) -> bool {
+        // Skip the marker index of a fully read block
+        let mut head = *self.head_index.get();
+        if head % LAP == BLOCK_CAP {
+            head += 1;
+        }
+        head == self.tail_index.load(Ordering::Acquire)
+    }
+
+    unsafe fn len(&self) -> usize {
+        // There is one marker index per LAP indices, below index i there are i / LAP markers
+        let slots = |index: usize| index - index / LAP;
+        let head = *self.head_index.get();
+        let tail = self.tail_index.load(Ordering::Acquire);
+        slots(tail) - slots(head)
+    }
+}
+
+impl<T> Drop for Queue<T> {
+    fn drop(&mut self) {
+        let tail = self.tail_index.load(Ordering::Relaxed);
+        let mut head = *self.head_index.get_mut();
+        let mut block = *self.head_block.get_mut();
+        // SAFETY: we have exclusive access, all the claimed slots are ready
+        unsafe {
+            while head != tail {
+                let offset = head % LAP;
+                if offset == BLOCK_CAP {
+                    let next = (*block).next.load(Ordering::Relaxed);
+                    drop(Box::from_raw(block));
+                    block = next;
+                } else {
+                    (*block).slots[offset].value.get_mut().assume_init_drop();
+                }
+                head += 1;
+            }
+            drop(Box::from_raw(block));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{block_channel, SendError, TryRecvError};
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn ping_pong() {
+        let (mut tx, mut rx) = block_channel();
+        tx.send(42).unwrap();
+        tx.send(7).unwrap();
+        assert_eq!(rx.recv(), Some(42));
+        assert_eq!(rx.recv(), Some(7));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        drop(tx);
+        assert_eq!(rx.recv(), None);
+    }
+
+    #[test]
+    fn closed_rx() {
+        let (mut tx, rx) = block_channel();
+        drop(rx);
+        assert_eq!(tx.send(12), Err(SendError(12)));
+    }
+
+    #[test]
+    fn across_blocks() {
+        let (mut tx, mut rx) = block_channel();
+        for i in 0..100 {
+            tx.send(i).unwrap();
+        }
+        for i in 0..100 {
+            assert_eq!(rx.recv(), Some(i));
+        }
+        // Interleaved sends and receives around the block boundaries
+        for i in 0..100 {
+            tx.send(i).unwrap();
+            assert_eq!(rx.try_recv(), Ok(i));
+        }
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn drop_pending_values() {
+        let value = Arc::new(());
+        let (mut tx, mut rx) = block_channel();
+        for _ in 0..100 {
+            tx.send(Arc::clone(&value)).unwrap();
+        }
+        // Leave some values in the current block and the next ones
+        for _ in 0..40 {
+            rx.recv().unwrap();
+        }
+        drop(rx);
+        drop(tx);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn stress_senders() {
+        const SENDERS: usize = 16;
+        const MESSAGES: usize = 10_000;
+
+        let (tx, mut rx) = block_channel();
+        let handles: Vec<_> = (0..SENDERS)
+            .map(|i| {
+                let mut tx = tx.clone();
+                thread::spawn(move || {
+                    for j in 0..MESSAGES {
+                        tx.send((i, j)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        drop(tx);
+        // Values of a given sender are received in order
+        let mut next = [0; SENDERS];
+        for (i, j) in rx.iter() {
+            assert_eq!(next[i], j);
+            next[i] += 1;
+        }
+        assert!(next.iter().all(|&n| n == MESSAGES));
+        for h in handles {
+            h.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn stress_drop_while_sending() {
+        // Dropping the channel while senders race on block installation must not leak nor crash
+        for _ in 0..100 {
+            let value = Arc::new(());
+            let (tx, mut rx) = block_channel();
+            let handles: Vec<_> = (0..4)
+                .map(|_| {
+                    let mut tx = tx.clone();
+                    let value = Arc::clone(&value);
+                    thread::spawn(move || {
+                        for _ in 0..50 {
+                            if tx.send(Arc::clone(&value)).is_err() {
+                                break;
+                            }
+                        }
+                    })
+                })
+                .collect();
+            drop(tx);
+            rx.try_recv().ok();
+            drop(rx);
+            for h in handles {
+                h.join().unwrap();
+            }
+            assert_eq!(Arc::strong_count(&value), 1);
+        }
+    }
+}
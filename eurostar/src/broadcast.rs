@@ -0,0 +1,272 @@
+use crate::sync::{self, Arc, Condvar, Mutex};
+use crate::{BroadcastRecvError, SendError};
+
+// Broadcast channel: every receiver gets a clone of every value.
+// Values are written in a ring buffer shared by all the receivers, each one reading at its own cursor.
+// Senders never block: once the buffer is full the oldest value is overwritten, and receivers that
+// had not read it yet lag behind.
+struct Inner<T> {
+    // slots[pos % capacity] holds the value sent at position pos
+    slots: Vec<Option<T>>,
+    // Position of the next value to send
+    tail: u64,
+    senders: usize,
+    receivers: usize,
+}
+
+impl<T> Inner<T> {
+    // Position of the oldest value still in the buffer
+    fn head(&self) -> u64 {
+        self.tail.saturating_sub(self.slots.len() as u64)
+    }
+}
+
+struct Shared<T> {
+    inner: Mutex<Inner<T>>,
+    available: Condvar,
+}
+
+pub struct BroadcastSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> BroadcastSender<T> {
+    /// Fails if there is no receiver left, handing the value back.
+    pub fn send(&self, t: T) -> Result<(), SendError<T>> {
+        // Acquire lock
+        let mut inner = sync::lock(&self.shared.inner);
+        if inner.receivers == 0 {
+            return Err(SendError(t));
+        }
+        let capacity = inner.slots.len() as u64;
+        let index = (inner.tail % capacity) as usize;
+        // Overwrites the oldest value if the buffer is full
+        let old = inner.slots[index].replace(t);
+        inner.tail += 1;
+        // Release lock
+        drop(inner);
+        self.shared.available.notify_all();
+        // Dropped last: its Drop may panic
+        drop(old);
+        Ok(())
+    }
+
+    /// New receiver only getting the values sent from now on.
+    pub fn subscribe(&self) -> BroadcastReceiver<T> {
+        let mut inner = sync::lock(&self.shared.inner);
+        inner.receivers += 1;
+        BroadcastReceiver {
+            shared: Arc::clone(&self.shared),
+            next: inner.tail,
+        }
+    }
+}
+
+impl<T> Clone for BroadcastSender<T> {
+    fn clone(&self) -> Self {
+        sync::lock(&self.shared.inner).senders += 1;
+        BroadcastSender {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for BroadcastSender<T> {
+    fn drop(&mut self) {
+        let mut inner = sync::lock(&self.shared.inner);
+        inner.senders -= 1;
+        let was_last = inner.senders == 0;
+        drop(inner);
+        if was_last {
+            self.shared.available.notify_all();
+        }
+    }
+}
+
+pub struct BroadcastReceiver<T> {
+    shared: Arc<Shared<T>>,
+    // Position of the next value to receive
+    next: u64,
+}
+
+impl<T: Clone> BroadcastReceiver<T> {
+    /// Blocks until the next value is sent. Fails with `Lagged(n)` if the `n` values following
+    /// the last one received were overwritten, the next call then returns the oldest value left.
+    pub fn recv(&mut self) -> Result<T, BroadcastRecvError> {
+        let mut inner = sync::lock(&self.shared.inner);
+        loop {
+            let head = inner.head();
+            if self.next < head {
+                let missed = head - self.next;
+                self.next = head;
+                return Err(BroadcastRecvError::Lagged(missed));
+            }
+            if self.next < inner.tail {
+                let index = (self.next % inner.slots.len() as u64) as usize;
+                let t = inner.slots[index]
+                    .clone()
+                    .expect("value in the ring buffer");
+                // Only moves on once cloned: a panicking Clone does not skip the value
+                self.next += 1;
+                return Ok(t);
+            }
+            if inner.senders == 0 {
+                return Err(BroadcastRecvError::Disconnected);
+            }
+            inner = sync::wait(&self.shared.available, inner);
+        }
+    }
+}
+
+// The clone reads from the same position
+impl<T> Clone for BroadcastReceiver<T> {
+    fn clone(&self) -> Self {
+        sync::lock(&self.shared.inner).receivers += 1;
+        BroadcastReceiver {
+            shared: Arc::clone(&self.shared),
+            next: self.next,
+        }
+    }
+}
+
+impl<T> Drop for BroadcastReceiver<T> {
+    fn drop(&mut self) {
+        sync::lock(&self.shared.inner).receivers -= 1;
+    }
+}
+
+/// Channel where each receiver gets every value, keeping the last `capacity` ones for slow receivers.
+pub fn broadcast<T: Clone>(capacity: usize) -> (BroadcastSender<T>, BroadcastReceiver<T>) {
+    assert!(capacity > 0, "broadcast capacity must be positive");
+    let inner = Inner {
+        slots: (0..capacity).map(|_| None).collect(),
+        tail: 0,
+        senders: 1,
+        receivers: 1,
+    };
+    let shared = Arc::new(Shared {
+        inner: Mutex::new(inner),
+        available: Condvar::new(),
+    });
+    let tx = BroadcastSender {
+        shared: Arc::clone(&shared),
+    };
+    let rx = BroadcastReceiver { shared, next: 0 };
+    (tx, rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn every_receiver_gets_every_value() {
+        let (tx, rx) = broadcast(4);
+        let handles: Vec<_> = (0..3)
+            .map(|_| {
+                let mut rx = rx.clone();
+                thread::spawn(move || {
+                    let mut received = Vec::new();
+                    while let Ok(i) = rx.recv() {
+                        received.push(i);
+                    }
+                    received
+                })
+            })
+            .collect();
+        drop(rx);
+        for i in 0..3 {
+            tx.send(i).unwrap();
+        }
+        drop(tx);
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), vec![0, 1, 2]);
+        }
+    }
+
+    #[test]
+    fn lagged() {
+        let (tx, mut rx) = broadcast(2);
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        // 0, 1 and 2 were overwritten
+        assert_eq!(rx.recv(), Err(BroadcastRecvError::Lagged(3)));
+        assert_eq!(rx.recv(), Ok(3));
+        assert_eq!(rx.recv(), Ok(4));
+        drop(tx);
+        assert_eq!(rx.recv(), Err(BroadcastRecvError::Disconnected));
+    }
+
+    #[test]
+    fn subscribe() {
+        let (tx, mut rx1) = broadcast(4);
+        tx.send(1).unwrap();
+        let mut rx2 = tx.subscribe();
+        tx.send(2).unwrap();
+        assert_eq!(rx1.recv(), Ok(1));
+        assert_eq!(rx1.recv(), Ok(2));
+        // Only gets the values sent after subscribing
+        assert_eq!(rx2.recv(), Ok(2));
+    }
+
+    #[test]
+    fn panicking_clone() {
+        use std::panic::{self, AssertUnwindSafe};
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        // Panics on the first clone only
+        #[derive(Debug)]
+        struct Flaky(std::sync::Arc<AtomicBool>);
+
+        impl Clone for Flaky {
+            fn clone(&self) -> Self {
+                assert!(self.0.swap(true, Ordering::SeqCst), "clone panicked");
+                Flaky(self.0.clone())
+            }
+        }
+
+        let (tx, mut rx) = broadcast(2);
+        tx.send(Flaky(Default::default())).unwrap();
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| rx.recv())).is_err());
+        // The value is received again
+        assert!(rx.recv().is_ok());
+        drop(tx);
+        assert!(matches!(rx.recv(), Err(BroadcastRecvError::Disconnected)));
+    }
+
+    #[test]
+    fn panicking_drop() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        // Panics when dropped, unlike its clones
+        struct D(i32, bool);
+
+        impl Clone for D {
+            fn clone(&self) -> Self {
+                D(self.0, false)
+            }
+        }
+
+        impl Drop for D {
+            fn drop(&mut self) {
+                assert!(!self.1 || thread::panicking(), "drop panicked");
+            }
+        }
+
+        let (tx, mut rx) = broadcast(1);
+        tx.send(D(0, true)).unwrap();
+        // Overwrites the first value, which panics once the second one was sent
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| tx.send(D(1, false)))).is_err());
+        assert!(matches!(rx.recv(), Err(BroadcastRecvError::Lagged(1))));
+        assert_eq!(rx.recv().map(|d| d.0), Ok(1));
+    }
+
+    #[test]
+    fn closed_rx() {
+        let (tx, rx) = broadcast(1);
+        drop(rx);
+        assert_eq!(tx.send(12), Err(SendError(12)));
+    }
+}
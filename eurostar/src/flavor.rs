@@ -0,0 +1,100 @@
+use crate::select::Selectable;
+use crate::stats::Counters;
+use crate::{RecvTimeoutError, SendError, SendTimeoutError, TryRecvError, TrySendError};
+use std::collections::VecDeque;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+/// Backend of a channel: the state shared by its `Sender`s and `Receiver`s, which are generic over it.
+///
+/// Implemented by the flavors of this crate:
+/// - [`MutexFlavor`](crate::MutexFlavor), the default: Mutex + Condvar + VecDeque, unbounded, bounded or rendezvous.
+/// - [`ListFlavor`](crate::ListFlavor): unbounded, over a lock-free linked list of values.
+/// - [`BlockFlavor`](crate::BlockFlavor): unbounded, over a lock-free linked list of blocks of values.
+///
+/// Code generic over the flavor works with any of them.
+/// The trait is sealed: it can only be implemented in this crate, through its private `Selectable` supertrait.
+pub trait ChannelFlavor<T>: Selectable {
+    /// Progress of an async send across polls, besides the value itself.
+    type SendState: Default + Send + Sync;
+
+    /// Blocks while the channel is full. Fails if it is closed, handing the value back.
+    fn send(&self, t: T) -> Result<(), SendError<T>>;
+
+    /// Does not block: fails with `Full` if the channel has no room.
+    /// Flavors that never block send right away.
+    fn try_send(&self, t: T) -> Result<(), TrySendError<T>> {
+        self.send(t)
+            .map_err(|SendError(t)| TrySendError::Disconnected(t))
+    }
+
+    /// Blocks until `deadline` at most while the channel is full.
+    /// Flavors that never block send right away.
+    fn send_deadline(&self, t: T, _deadline: Instant) -> Result<(), SendTimeoutError<T>> {
+        self.send(t)
+            .map_err(|SendError(t)| SendTimeoutError::Disconnected(t))
+    }
+
+    /// Fails if the channel gets closed, handing back the values not sent.
+    fn send_all(&self, values: VecDeque<T>) -> Result<(), SendError<VecDeque<T>>>;
+
+    /// Sends without blocking the thread. `value` is taken once it is in the channel,
+    /// it is left there meanwhile. Flavors that never block send right away.
+    fn poll_send(
+        &self,
+        _cx: &mut Context<'_>,
+        value: &mut Option<T>,
+        _state: &mut Self::SendState,
+    ) -> Poll<Result<(), SendError<T>>> {
+        let t = value.take().expect("send future polled after completion");
+        Poll::Ready(self.send(t))
+    }
+
+    /// Called when an async send is dropped before completion.
+    fn cancel_send(&self, _state: &mut Self::SendState) {}
+
+    // `buffer` is the receiver's own buffer: a flavor may move values there to take its lock less often.
+    // It is only used once empty.
+
+    fn recv(&self, buffer: &mut VecDeque<T>) -> Option<T>;
+
+    fn try_recv(&self, buffer: &mut VecDeque<T>) -> Result<T, TryRecvError>;
+
+    fn recv_deadline(
+        &self,
+        buffer: &mut VecDeque<T>,
+        deadline: Instant,
+    ) -> Result<T, RecvTimeoutError>;
+
+    /// Moves all the values waiting in the channel to `out`, returns how many.
+    fn drain_into(&self, out: &mut Vec<T>) -> usize;
+
+    fn add_sender(&self);
+
+    fn drop_sender(&self);
+
+    /// Values in the `buffer` of the receiver being cloned go back to the channel, for the clone to see them.
+    fn add_receiver(&self, buffer: &mut VecDeque<T>);
+
+    /// Values left in the receiver's `buffer` go back to the other receivers.
+    fn drop_receiver(&self, buffer: &mut VecDeque<T>);
+
+    /// Values waiting in the channel, not counting the ones moved to a receiver's buffer.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn senders(&self) -> usize;
+
+    /// Sending fails: closed explicitly or all the receivers are gone.
+    fn is_closed(&self) -> bool;
+
+    /// No more values will be sent: closed explicitly or all the senders are gone.
+    fn is_disconnected(&self) -> bool;
+
+    fn close(&self);
+
+    fn counters(&self) -> &Counters;
+}
@@ -0,0 +1,225 @@
+use crate::select::Selectable;
+use crate::{ChannelFlavor, MutexFlavor, Receiver, SendError, Sender, TryRecvError};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+// Async versions of send() and recv(): futures registering their Waker in the channel's shared state.
+// They do not depend on any runtime and work along the blocking API on the same channel.
+
+impl<T, F: ChannelFlavor<T>> Sender<T, F> {
+    /// Resolves once the value is in the channel, without blocking the thread on a full bounded channel.
+    /// Dropping the future cancels the send, unless the value was already handed to a rendezvous channel.
+    pub fn send_async(&mut self, t: T) -> SendFut<'_, T, F> {
+        SendFut {
+            tx: self,
+            send: AsyncSend::new(t),
+        }
+    }
+}
+
+impl<T, F: ChannelFlavor<T>> Receiver<T, F> {
+    /// Resolves to the next value, or to None once all the senders are gone.
+    pub fn recv_async(&mut self) -> RecvFut<'_, T, F> {
+        RecvFut { rx: self }
+    }
+
+    /// Polls for the next value, registering the task's Waker in the channel if there is none.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.unwatch();
+        match self.try_recv() {
+            Ok(t) => return Poll::Ready(Some(t)),
+            Err(TryRecvError::Disconnected) => return Poll::Ready(None),
+            Err(TryRecvError::Empty) => {}
+        }
+        self.token = Some(self.flavor.watch(cx.waker()));
+        // Check again now that we are watching so that no wakeup is missed
+        match self.try_recv() {
+            Ok(t) => {
+                self.unwatch();
+                Poll::Ready(Some(t))
+            }
+            Err(TryRecvError::Disconnected) => {
+                self.unwatch();
+                Poll::Ready(None)
+            }
+            Err(TryRecvError::Empty) => Poll::Pending,
+        }
+    }
+
+    pub(crate) fn unwatch(&mut self) {
+        if let Some(token) = self.token.take() {
+            self.flavor.unwatch(token);
+        }
+    }
+}
+
+// Async send in progress: the value until it is in the channel, and the flavor's own state
+pub(crate) struct AsyncSend<T, S> {
+    pub(crate) value: Option<T>,
+    pub(crate) state: S,
+}
+
+impl<T, S: Default> AsyncSend<T, S> {
+    pub(crate) fn new(t: T) -> Self {
+        AsyncSend {
+            value: Some(t),
+            state: S::default(),
+        }
+    }
+}
+
+pub struct SendFut<'a, T, F: ChannelFlavor<T> = MutexFlavor<T>> {
+    tx: &'a mut Sender<T, F>,
+    send: AsyncSend<T, F::SendState>,
+}
+
+// The value is never pinned, only moved in the channel
+impl<T, F: ChannelFlavor<T>> Unpin for SendFut<'_, T, F> {}
+
+impl<T, F: ChannelFlavor<T>> Future for SendFut<'_, T, F> {
+    type Output = Result<(), SendError<T>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let flavor = &this.tx.flavor;
+        flavor
+            .poll_send(cx, &mut this.send.value, &mut this.send.state)
+            .map(|res| res.inspect(|_| flavor.counters().on_send(1)))
+    }
+}
+
+impl<T, F: ChannelFlavor<T>> Drop for SendFut<'_, T, F> {
+    fn drop(&mut self) {
+        self.tx.flavor.cancel_send(&mut self.send.state);
+    }
+}
+
+pub struct RecvFut<'a, T, F: ChannelFlavor<T> = MutexFlavor<T>> {
+    rx: &'a mut Receiver<T, F>,
+}
+
+impl<T, F: ChannelFlavor<T>> Future for RecvFut<'_, T, F> {
+    type Output = Option<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.get_mut().rx.poll_recv(cx)
+    }
+}
+
+impl<T, F: ChannelFlavor<T>> Drop for RecvFut<'_, T, F> {
+    fn drop(&mut self) {
+        self.rx.unwatch();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::waker::block_on;
+    use crate::{
+        block_channel, channel, list_channel, sync_channel, ChannelFlavor, Receiver, SendError,
+        Sender,
+    };
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn recv_async() {
+        let (mut tx, mut rx) = channel();
+        tx.send(42).unwrap();
+        assert_eq!(block_on(rx.recv_async()), Some(42));
+        drop(tx);
+        assert_eq!(block_on(rx.recv_async()), None);
+    }
+
+    #[test]
+    fn recv_async_from_thread() {
+        fn check<F>((mut tx, mut rx): (Sender<i32, F>, Receiver<i32, F>))
+        where
+            F: ChannelFlavor<i32> + Send + Sync + 'static,
+        {
+            let t = thread::spawn(move || {
+                for i in 0..10 {
+                    thread::sleep(Duration::from_millis(1));
+                    tx.send(i).unwrap();
+                }
+            });
+            let received = block_on(async {
+                let mut received = Vec::new();
+                while let Some(i) = rx.recv_async().await {
+                    received.push(i);
+                }
+                received
+            });
+            assert_eq!(received, (0..10).collect::<Vec<_>>());
+            t.join().unwrap();
+        }
+        check(channel());
+        check(sync_channel(1));
+        check(list_channel());
+        check(block_channel());
+    }
+
+    #[test]
+    fn send_async_bounded() {
+        let (mut tx, mut rx) = sync_channel(1);
+        let t = thread::spawn(move || {
+            block_on(async {
+                for i in 0..10 {
+                    // Waits for the blocking receiver to make room
+                    tx.send_async(i).await.unwrap();
+                }
+            })
+        });
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(rx.iter().collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+        t.join().unwrap();
+    }
+
+    #[test]
+    fn send_async_rendezvous() {
+        let (mut tx, mut rx) = sync_channel(0);
+        let t = thread::spawn(move || block_on(tx.send_async(42)));
+        thread::sleep(Duration::from_millis(20));
+        // The future resolves once the value was taken
+        assert!(!t.is_finished());
+        assert_eq!(rx.recv(), Some(42));
+        assert_eq!(t.join().unwrap(), Ok(()));
+    }
+
+    #[test]
+    fn send_async_closed() {
+        let (mut tx, rx) = sync_channel(1);
+        tx.send(1).unwrap();
+        let t = thread::spawn(move || block_on(tx.send_async(2)));
+        thread::sleep(Duration::from_millis(20));
+        drop(rx);
+        assert_eq!(t.join().unwrap(), Err(SendError(2)));
+    }
+
+    #[test]
+    fn inline_waker_rendezvous() {
+        use std::sync::{Arc, Mutex};
+        use std::task::{Context, Wake, Waker};
+
+        // Polls the receiver right away when woken up
+        struct Inline(Mutex<Receiver<i32>>, Mutex<Option<i32>>);
+
+        impl Wake for Inline {
+            fn wake(self: Arc<Self>) {
+                if let Ok(t) = self.0.lock().unwrap().try_recv() {
+                    *self.1.lock().unwrap() = Some(t);
+                }
+            }
+        }
+
+        let (mut tx, rx) = sync_channel(0);
+        let inline = Arc::new(Inline(Mutex::new(rx), Mutex::new(None)));
+        let waker = Waker::from(Arc::clone(&inline));
+        let mut cx = Context::from_waker(&waker);
+        assert!(inline.0.lock().unwrap().poll_recv(&mut cx).is_pending());
+        // The Waker is not run with the channel locked
+        tx.send(42).unwrap();
+        assert_eq!(*inline.1.lock().unwrap(), Some(42));
+    }
+}
@@ -0,0 +1,531 @@
+//! Channels between processes, over connected Unix domain sockets.
+//!
+//! Each end of the socket becomes a regular [`Sender`] or [`Receiver`] with the [`IpcFlavor`] backend,
+//! so code generic over [`ChannelFlavor`] runs unchanged in or out of process.
+//! Values are serialized by a [`Codec`] and written to the socket as length-prefixed frames.
+//! A frame longer than [`Codec::max_len`] disconnects the channel, as does a value failing to decode.
+//! The sending end checks the length first: sending a value encoding to a longer frame fails,
+//! handing the value back, but leaves the channel open.
+//!
+//! ```
+//! # use eurostar::ipc::{self, Bytes};
+//! let (mut tx, mut rx) = ipc::channel::<String, _>(Bytes).unwrap();
+//! tx.send("ping".to_string()).unwrap();
+//! assert_eq!(rx.recv().as_deref(), Some("ping"));
+//! ```
+use crate::mutex;
+use crate::select::Selectable;
+use crate::stats::Counters;
+use crate::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use crate::sync::{self, thread, Arc, Mutex};
+use crate::{
+    ChannelFlavor, Receiver, RecvTimeoutError, SendError, SendTimeoutError, Sender, TryRecvError,
+    TrySendError,
+};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+use std::net::Shutdown;
+use std::os::unix::net::UnixStream;
+use std::task::Waker;
+use std::time::Instant;
+
+/// Default [`Codec::max_len`]: 16 MiB.
+pub const MAX_LEN: usize = 16 << 20;
+
+/// Serialization of the values sent over an IPC channel.
+pub trait Codec<T> {
+    fn encode(&self, t: &T, out: &mut Vec<u8>);
+
+    /// A value failing to decode disconnects the channel.
+    fn decode(&self, bytes: &[u8]) -> io::Result<T>;
+
+    /// Longest encoded value accepted by the receiving end: the length read off the socket is not
+    /// trusted, a longer frame disconnects the channel instead of being allocated.
+    fn max_len(&self) -> usize {
+        MAX_LEN
+    }
+}
+
+/// Sends byte buffers and strings as they are.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bytes;
+
+impl Codec<Vec<u8>> for Bytes {
+    fn encode(&self, t: &Vec<u8>, out: &mut Vec<u8>) {
+        out.extend_from_slice(t);
+    }
+
+    fn decode(&self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        Ok(bytes.to_vec())
+    }
+}
+
+impl Codec<String> for Bytes {
+    fn encode(&self, t: &String, out: &mut Vec<u8>) {
+        out.extend_from_slice(t.as_bytes());
+    }
+
+    fn decode(&self, bytes: &[u8]) -> io::Result<String> {
+        String::from_utf8(bytes.to_vec()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// Backend of one end of an IPC channel: the senders write to the socket, the receivers
+/// get the values read off the socket by a thread of their own.
+pub struct IpcFlavor<T, C> {
+    stream: UnixStream,
+    codec: Arc<C>,
+    // Serializes the frames written by the senders of this end
+    writing: Mutex<()>,
+    senders: AtomicUsize,
+    receivers: AtomicUsize,
+    // Sending fails: closed explicitly, or the peer is gone
+    closed: AtomicBool,
+    // Values read off the socket. The reader thread is its only sender,
+    // so it is disconnected once the peer is gone (or right away on a sending end).
+    queue: Arc<mutex::Shared<T>>,
+}
+
+impl<T, C> IpcFlavor<T, C> {
+    fn new(stream: UnixStream, codec: Arc<C>, senders: usize, receivers: usize) -> Self {
+        IpcFlavor {
+            stream,
+            codec,
+            writing: Mutex::new(()),
+            senders: AtomicUsize::new(senders),
+            receivers: AtomicUsize::new(receivers),
+            closed: AtomicBool::new(false),
+            queue: Arc::new(mutex::Shared::new(None)),
+        }
+    }
+}
+
+// Reads the frames off the socket until the peer or the receivers are gone
+fn read_frames<T, C: Codec<T>>(
+    mut stream: UnixStream,
+    codec: Arc<C>,
+    queue: Arc<mutex::Shared<T>>,
+) {
+    let mut buf = Vec::new();
+    loop {
+        let mut len = [0; 4];
+        if stream.read_exact(&mut len).is_err() {
+            break;
+        }
+        let len = u32::from_le_bytes(len) as usize;
+        if len > codec.max_len() {
+            break;
+        }
+        buf.resize(len, 0);
+        if stream.read_exact(&mut buf).is_err() {
+            break;
+        }
+        let t = match codec.decode(&buf) {
+            Ok(t) => t,
+            Err(_) => break,
+        };
+        if queue.send(t).is_err() {
+            break;
+        }
+    }
+    // Make the peer's sends fail rather than fill up the socket
+    let _ = stream.shutdown(Shutdown::Read);
+    // The receivers get the values left, then None
+    queue.drop_sender();
+}
+
+impl<T, C: Codec<T>> IpcFlavor<T, C> {
+    // Blocks while the socket's buffer is full, until `deadline` if any
+    fn send_until(&self, t: T, deadline: Option<Instant>) -> Result<(), SendTimeoutError<T>> {
+        if self.is_closed() {
+            return Err(SendTimeoutError::Disconnected(t));
+        }
+        let mut frame = vec![0; 4];
+        self.codec.encode(&t, &mut frame);
+        // Too long for the receiving end: fail without tearing down the channel
+        let len = match u32::try_from(frame.len() - 4) {
+            Ok(len) if frame.len() - 4 <= self.codec.max_len() => len,
+            _ => return Err(SendTimeoutError::Disconnected(t)),
+        };
+        frame[..4].copy_from_slice(&len.to_le_bytes());
+        let _writing = sync::lock(&self.writing);
+        let res = match deadline {
+            Some(deadline) => write_frame_until(&self.stream, &frame, deadline),
+            None => (&self.stream).write_all(&frame).map(|_| true),
+        };
+        match res {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(SendTimeoutError::Timeout(t)),
+            Err(_) => {
+                self.closed.store(true, Ordering::SeqCst);
+                Err(SendTimeoutError::Disconnected(t))
+            }
+        }
+    }
+}
+
+// Returns false if none of the frame could be written before `deadline`, right away if it is past.
+// Once started, the frame is written to the end so as not to break the stream.
+// The writing lock must be held: the socket's timeout and blocking mode are changed meanwhile.
+fn write_frame_until(mut stream: &UnixStream, frame: &[u8], deadline: Instant) -> io::Result<bool> {
+    let written = loop {
+        let now = Instant::now();
+        let res = if deadline <= now {
+            stream.set_nonblocking(true)?;
+            let res = stream.write(frame);
+            stream.set_nonblocking(false)?;
+            res
+        } else {
+            stream.set_write_timeout(Some(deadline - now))?;
+            let res = stream.write(frame);
+            stream.set_write_timeout(None)?;
+            res
+        };
+        match res {
+            Ok(written) => break written,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                if Instant::now() >= deadline {
+                    return Ok(false);
+                }
+            }
+            Err(e) => return Err(e),
+        }
+    };
+    stream.write_all(&frame[written..])?;
+    Ok(true)
+}
+
+impl<T, C: Codec<T>> ChannelFlavor<T> for IpcFlavor<T, C> {
+    // Async sends block on a full socket buffer
+    type SendState = ();
+
+    fn send(&self, t: T) -> Result<(), SendError<T>> {
+        self.send_until(t, None)
+            .map_err(|e| SendError(e.into_inner()))
+    }
+
+    // Full while the socket's buffer is
+    fn try_send(&self, t: T) -> Result<(), TrySendError<T>> {
+        self.send_until(t, Some(Instant::now()))
+            .map_err(|e| match e {
+                SendTimeoutError::Timeout(t) => TrySendError::Full(t),
+                SendTimeoutError::Disconnected(t) => TrySendError::Disconnected(t),
+            })
+    }
+
+    fn send_deadline(&self, t: T, deadline: Instant) -> Result<(), SendTimeoutError<T>> {
+        self.send_until(t, Some(deadline))
+    }
+
+    fn send_all(&self, mut values: VecDeque<T>) -> Result<(), SendError<VecDeque<T>>> {
+        while let Some(t) = values.pop_front() {
+            if let Err(SendError(t)) = self.send(t) {
+                values.push_front(t);
+                return Err(SendError(values));
+            }
+        }
+        Ok(())
+    }
+
+    fn recv(&self, buffer: &mut VecDeque<T>) -> Option<T> {
+        self.queue.recv(buffer)
+    }
+
+    fn try_recv(&self, buffer: &mut VecDeque<T>) -> Result<T, TryRecvError> {
+        self.queue.try_recv(buffer)
+    }
+
+    fn recv_deadline(
+        &self,
+        buffer: &mut VecDeque<T>,
+        deadline: Instant,
+    ) -> Result<T, RecvTimeoutError> {
+        self.queue.recv_deadline(buffer, deadline)
+    }
+
+    fn drain_into(&self, out: &mut Vec<T>) -> usize {
+        self.queue.drain_into(out)
+    }
+
+    fn add_sender(&self) {
+        self.senders.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn drop_sender(&self) {
+        // The last sender of this end is gone: the peer's receivers get None
+        if self.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            let _ = self.stream.shutdown(Shutdown::Write);
+        }
+    }
+
+    fn add_receiver(&self, buffer: &mut VecDeque<T>) {
+        self.receivers.fetch_add(1, Ordering::Relaxed);
+        self.queue.add_receiver(buffer);
+    }
+
+    fn drop_receiver(&self, buffer: &mut VecDeque<T>) {
+        self.queue.drop_receiver(buffer);
+        // The last receiver of this end is gone: stops the reader thread, the peer's sends fail
+        if self.receivers.fetch_sub(1, Ordering::AcqRel) == 1 {
+            let _ = self.stream.shutdown(Shutdown::Read);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    // The senders of a sending end, 1 on a receiving end while the peer is connected
+    fn senders(&self) -> usize {
+        self.senders.load(Ordering::Acquire) + self.queue.senders()
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    fn is_disconnected(&self) -> bool {
+        self.queue.is_disconnected()
+    }
+
+    fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        let _ = self.stream.shutdown(Shutdown::Both);
+        self.queue.close();
+    }
+
+    fn counters(&self) -> &Counters {
+        self.queue.counters()
+    }
+}
+
+impl<T, C> Selectable for IpcFlavor<T, C> {
+    fn is_ready(&self) -> bool {
+        self.queue.is_ready()
+    }
+
+    fn watch(&self, waker: &Waker) -> usize {
+        self.queue.watch(waker)
+    }
+
+    fn unwatch(&self, token: usize) {
+        self.queue.unwatch(token)
+    }
+}
+
+pub type IpcSender<T, C> = Sender<T, IpcFlavor<T, C>>;
+pub type IpcReceiver<T, C> = Receiver<T, IpcFlavor<T, C>>;
+
+/// Sending end over a connected socket, whose other end is a [`receiver()`], possibly in another process.
+pub fn sender<T, C: Codec<T>>(stream: UnixStream, codec: C) -> IpcSender<T, C> {
+    let flavor = IpcFlavor::new(stream, Arc::new(codec), 1, 0);
+    // Nothing is ever read on this end
+    flavor.queue.drop_sender();
+    Sender::new(Arc::new(flavor))
+}
+
+/// Receiving end over a connected socket, whose other end is a [`sender()`], possibly in another process.
+/// Spawns a thread reading the values off the socket.
+pub fn receiver<T, C>(stream: UnixStream, codec: C) -> io::Result<IpcReceiver<T, C>>
+where
+    T: Send + 'static,
+    C: Codec<T> + Send + Sync + 'static,
+{
+    let reader = stream.try_clone()?;
+    let flavor = IpcFlavor::new(stream, Arc::new(codec), 0, 1);
+    let codec = Arc::clone(&flavor.codec);
+    let queue = Arc::clone(&flavor.queue);
+    thread::spawn(move || read_frames(reader, codec, queue));
+    Ok(Receiver::new(Arc::new(flavor)))
+}
+
+/// Both ends over a new socket pair.
+pub fn channel<T, C>(codec: C) -> io::Result<(IpcSender<T, C>, IpcReceiver<T, C>)>
+where
+    T: Send + 'static,
+    C: Codec<T> + Clone + Send + Sync + 'static,
+{
+    let (tx, rx) = UnixStream::pair()?;
+    Ok((sender(tx, codec.clone()), receiver(rx, codec)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Select, TryRecvError};
+    use std::convert::TryInto;
+    use std::os::unix::net::UnixListener;
+    use std::time::Duration;
+
+    // Fixed-size little-endian integers
+    #[derive(Clone)]
+    struct U64;
+
+    impl Codec<u64> for U64 {
+        fn encode(&self, t: &u64, out: &mut Vec<u8>) {
+            out.extend_from_slice(&t.to_le_bytes());
+        }
+
+        fn decode(&self, bytes: &[u8]) -> io::Result<u64> {
+            let bytes = bytes
+                .try_into()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            Ok(u64::from_le_bytes(bytes))
+        }
+    }
+
+    #[test]
+    fn ping_pong() {
+        let (mut tx, mut rx) = channel(U64).unwrap();
+        tx.send(42).unwrap();
+        tx.send_all([7, 12]).unwrap();
+        assert_eq!(rx.recv(), Some(42));
+        assert_eq!(rx.recv(), Some(7));
+        assert_eq!(rx.recv(), Some(12));
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Timeout)
+        );
+    }
+
+    #[test]
+    fn closed_tx() {
+        let (mut tx, mut rx) = channel::<String, _>(Bytes).unwrap();
+        let tx2 = tx.clone();
+        tx.send("last".to_string()).unwrap();
+        drop(tx);
+        drop(tx2);
+        // The values sent before are still received
+        assert_eq!(rx.recv().as_deref(), Some("last"));
+        assert_eq!(rx.recv(), None);
+        assert_eq!(rx.sender_count(), 0);
+    }
+
+    #[test]
+    fn closed_rx() {
+        let (mut tx, rx) = channel(U64).unwrap();
+        drop(rx);
+        assert_eq!(tx.send(12), Err(SendError(12)));
+        assert!(tx.is_closed());
+    }
+
+    #[test]
+    fn decode_error() {
+        let (a, b) = UnixStream::pair().unwrap();
+        let mut tx = sender::<Vec<u8>, _>(a, Bytes);
+        // Invalid UTF-8 disconnects the channel
+        let mut rx = receiver::<String, _>(b, Bytes).unwrap();
+        tx.send(b"ok".to_vec()).unwrap();
+        tx.send(vec![0xff]).unwrap();
+        assert_eq!(rx.recv().as_deref(), Some("ok"));
+        assert_eq!(rx.recv(), None);
+    }
+
+    #[test]
+    fn frame_too_long() {
+        let (mut a, b) = UnixStream::pair().unwrap();
+        let mut rx = receiver::<Vec<u8>, _>(b, Bytes).unwrap();
+        // Disconnects rather than allocating 4 GiB
+        a.write_all(&u32::MAX.to_le_bytes()).unwrap();
+        assert_eq!(rx.recv(), None);
+    }
+
+    #[test]
+    fn value_too_long() {
+        // Bytes with a small max_len
+        #[derive(Clone)]
+        struct Short;
+
+        impl Codec<Vec<u8>> for Short {
+            fn encode(&self, t: &Vec<u8>, out: &mut Vec<u8>) {
+                Bytes.encode(t, out)
+            }
+
+            fn decode(&self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+                Bytes.decode(bytes)
+            }
+
+            fn max_len(&self) -> usize {
+                4
+            }
+        }
+
+        let (mut tx, mut rx) = channel(Short).unwrap();
+        // The value is handed back and the channel stays open
+        assert_eq!(tx.send(vec![0; 5]), Err(SendError(vec![0; 5])));
+        assert_eq!(
+            tx.try_send(vec![0; 5]),
+            Err(TrySendError::Disconnected(vec![0; 5]))
+        );
+        assert!(!tx.is_closed());
+        tx.send(vec![1; 4]).unwrap();
+        assert_eq!(rx.recv(), Some(vec![1; 4]));
+    }
+
+    #[test]
+    fn full() {
+        let (a, b) = UnixStream::pair().unwrap();
+        let mut tx = sender(a, U64);
+        // Nothing reads the socket: fill up its buffer
+        let mut sent = 0;
+        let full = loop {
+            match tx.try_send(sent) {
+                Ok(()) => sent += 1,
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(full, TrySendError::Full(sent));
+        assert_eq!(
+            tx.send_timeout(sent, Duration::from_millis(20)),
+            Err(SendTimeoutError::Timeout(sent))
+        );
+        // The frames sent are intact
+        drop(tx);
+        let mut rx = receiver(b, U64).unwrap();
+        assert_eq!(rx.iter().collect::<Vec<_>>(), (0..sent).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn connect() {
+        let path = std::env::temp_dir().join(format!("eurostar-ipc-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        // Stands for another process connecting to the socket
+        let t = std::thread::spawn({
+            let path = path.clone();
+            move || {
+                let mut tx = sender(UnixStream::connect(path).unwrap(), U64);
+                for i in 0..100 {
+                    tx.send(i).unwrap();
+                }
+            }
+        });
+        let (stream, _) = listener.accept().unwrap();
+        let mut rx = receiver(stream, U64).unwrap();
+        assert_eq!(rx.iter().collect::<Vec<_>>(), (0..100).collect::<Vec<_>>());
+        t.join().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn select() {
+        let (_tx1, rx1) = crate::channel::<u64>();
+        let (mut tx2, mut rx2) = channel(U64).unwrap();
+        let mut sel = Select::new();
+        sel.recv(&rx1);
+        sel.recv(&rx2);
+        assert_eq!(sel.try_ready(), None);
+        tx2.send(1).unwrap();
+        // Woken up once the reader thread got the value
+        assert_eq!(sel.ready(), 1);
+        assert_eq!(rx2.try_recv(), Ok(1));
+        assert_eq!(rx2.try_recv(), Err(TryRecvError::Empty));
+    }
+}
@@ -1,116 +1,412 @@
+use crate::sync::Arc;
 use std::collections::VecDeque;
-use std::sync::{Arc, Condvar, Mutex};
-/// Different flavors of channels:
-/// - Synchronous channels: Channel where send() can block. Limited capacity.
-///   - Mutex + Condvar + VecDeque
-///   - Atomic VecDeque (atomic queue) + thread::park + thread::Thread::notify
-/// - Asynchronous channels: Channel where send() cannot block. Unbounded.
-///   - Mutex + Condvar + VecDeque
-///   - Mutex + Condvar + LinkedList
-///   - Atomic linked list, linked list of T
-///   - Atomic block linked list, linked list of atomic VecDeque<T>
-/// - Rendezvous channels: Synchronous with capacity = 0. Used for thread synchronization.
-/// - Oneshot channels: Any capacity. In practice, only one call to send().
-
-#[derive(Default)]
-struct Inner<T> {
-    queue: VecDeque<T>,
-    senders: usize,
-}
-struct Shared<T> {
-    inner: Mutex<Inner<T>>,
-    available: Condvar,
-}
-pub struct Sender<T> {
-    shared: Arc<Shared<T>>,
-}
-
-impl<T> Sender<T> {
-    pub fn send(&mut self, t: T) {
-        // Acquire lock
-        let mut inner = self.shared.inner.lock().unwrap();
-        inner.queue.push_back(t);
-        // Release lock
-        drop(inner);
-        // Notify blocked thread
-        self.shared.available.notify_one();
-    }
-}
-
-impl<T> Clone for Sender<T> {
+use std::marker::PhantomData;
+use std::sync::PoisonError;
+use std::time::{Duration, Instant};
+
+mod block;
+mod broadcast;
+mod error;
+mod flavor;
+mod future;
+#[cfg(all(unix, not(loom)))]
+pub mod ipc;
+mod list;
+mod lockfree;
+mod mutex;
+mod oneshot;
+mod priority;
+mod select;
+mod stats;
+#[cfg(feature = "futures")]
+mod stream;
+mod sync;
+mod timer;
+mod waker;
+mod watch;
+
+pub use broadcast::{broadcast, BroadcastReceiver, BroadcastSender};
+pub use error::{
+    BroadcastRecvError, RecvError, RecvTimeoutError, SendError, SendTimeoutError, TryRecvError,
+    TrySendError,
+};
+pub use flavor::ChannelFlavor;
+pub use future::{RecvFut, SendFut};
+pub use oneshot::{oneshot, OneshotReceiver, OneshotSender};
+pub use priority::{priority_channel, PriorityReceiver, PrioritySender};
+pub use select::Select;
+#[cfg(feature = "stats")]
+pub use stats::Stats;
+pub use timer::{after, tick};
+pub use watch::{watch, WatchReceiver, WatchRef, WatchSender};
+
+// Different flavors of channels:
+// - Synchronous channels: Channel where send() can block. Limited capacity.
+//   - Mutex + Condvar + VecDeque
+//   - Atomic VecDeque (atomic queue) + thread::park + thread::Thread::notify
+// - Asynchronous channels: Channel where send() cannot block. Unbounded.
+//   - Mutex + Condvar + VecDeque
+//   - Mutex + Condvar + LinkedList
+//   - Atomic linked list, linked list of T
+//   - Atomic block linked list, linked list of atomic VecDeque<T>
+// - Rendezvous channels: Synchronous with capacity = 0. Used for thread synchronization.
+// - Oneshot channels: Any capacity. In practice, only one call to send().
+
+/// Mutex + Condvar + VecDeque backend, unbounded, bounded or rendezvous. The default one.
+pub type MutexFlavor<T> = mutex::Shared<T>;
+/// Unbounded backend over a lock-free queue, allocating values one at a time.
+pub type ListFlavor<T> = lockfree::Shared<list::Queue<T>>;
+/// Unbounded backend over a lock-free queue, allocating values by blocks.
+pub type BlockFlavor<T> = lockfree::Shared<block::Queue<T>>;
+
+pub struct Sender<T, F: ChannelFlavor<T> = MutexFlavor<T>> {
+    flavor: Arc<F>,
+    // Value given to the Sink API, sent by the next poll
+    #[cfg(feature = "futures")]
+    sink: Option<future::AsyncSend<T, F::SendState>>,
+    // The values only go through the flavor
+    marker: PhantomData<fn(T)>,
+}
+
+impl<T, F: ChannelFlavor<T>> Sender<T, F> {
+    /// Fails if the receiver is gone, handing the value back.
+    pub fn send(&mut self, t: T) -> Result<(), SendError<T>> {
+        self.flavor
+            .send(t)
+            .inspect(|_| self.flavor.counters().on_send(1))
+    }
+
+    /// Does not block: fails with `Full` if the channel has no room left,
+    /// or if no receiver is waiting for the value on a rendezvous channel.
+    pub fn try_send(&mut self, t: T) -> Result<(), TrySendError<T>> {
+        self.flavor
+            .try_send(t)
+            .inspect(|_| self.flavor.counters().on_send(1))
+    }
+
+    /// Blocks for at most `timeout` while the channel is full, or waiting for the hand-off on a
+    /// rendezvous channel. Fails with `Timeout` then, handing the value back.
+    pub fn send_timeout(&mut self, t: T, timeout: Duration) -> Result<(), SendTimeoutError<T>> {
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self
+                .flavor
+                .send_deadline(t, deadline)
+                .inspect(|_| self.flavor.counters().on_send(1)),
+            // Too far to be represented: no deadline
+            None => self
+                .send(t)
+                .map_err(|SendError(t)| SendTimeoutError::Disconnected(t)),
+        }
+    }
+
+    /// Sends all the values at once, taking the lock and notifying the receivers only once
+    /// on unbounded channels, and once per batch filling up the queue on bounded ones.
+    /// Fails if the receivers are gone, handing back the values not sent.
+    pub fn send_all<I: IntoIterator<Item = T>>(
+        &mut self,
+        values: I,
+    ) -> Result<(), SendError<Vec<T>>> {
+        let values: VecDeque<T> = values.into_iter().collect();
+        let len = values.len();
+        let res = self.flavor.send_all(values);
+        let not_sent = match &res {
+            Ok(()) => 0,
+            Err(SendError(values)) => values.len(),
+        };
+        self.flavor.counters().on_send(len - not_sent);
+        res.map_err(|SendError(values)| SendError(values.into()))
+    }
+
+    /// Number of values waiting in the channel. Values already moved to a receiver's
+    /// own buffer (unbounded channel with a single receiver) are not counted.
+    pub fn len(&self) -> usize {
+        self.flavor.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn sender_count(&self) -> usize {
+        self.flavor.senders()
+    }
+
+    /// The channel was closed or all the receivers are gone: sending fails.
+    pub fn is_closed(&self) -> bool {
+        self.flavor.is_closed()
+    }
+
+    /// Closes the channel for all the handles: sending fails from now on,
+    /// the values already sent can still be received.
+    pub fn close(&self) {
+        self.flavor.close()
+    }
+
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> Stats {
+        self.flavor.counters().stats()
+    }
+}
+
+impl<T, F: ChannelFlavor<T>> Clone for Sender<T, F> {
     fn clone(&self) -> Self {
-        // Acquire lock
-        let mut inner = self.shared.inner.lock().unwrap();
-        // Increase senders counter
-        inner.senders += 1;
-        // Release lock
-        drop(inner);
+        self.flavor.add_sender();
         Self {
-            shared: Arc::clone(&self.shared),
+            flavor: Arc::clone(&self.flavor),
+            #[cfg(feature = "futures")]
+            sink: None,
+            marker: PhantomData,
         }
     }
 }
 
-impl<T> Drop for Sender<T> {
+impl<T, F: ChannelFlavor<T>> Drop for Sender<T, F> {
     fn drop(&mut self) {
-        // Acquire lock
-        let mut inner = self.shared.inner.lock().unwrap();
-        // Decrease senders counter
-        inner.senders -= 1;
-        let was_last = inner.senders == 0;
-        // Release lock
-        drop(inner);
-
-        // If it was the last sender, notify the receiver thread
-        // so that it doesn't hang waiting for a sender that does not exists
-        if was_last {
-            self.shared.available.notify_one()
+        #[cfg(feature = "futures")]
+        if let Some(send) = &mut self.sink {
+            self.flavor.cancel_send(&mut send.state);
         }
+        self.flavor.drop_sender();
     }
 }
 
-pub struct Receiver<T> {
-    shared: Arc<Shared<T>>,
-    buffer: VecDeque<T>,
+pub struct Receiver<T, F: ChannelFlavor<T> = MutexFlavor<T>> {
+    flavor: Arc<F>,
+    // Values stolen from the shared queue (unbounded mutex flavor with a single receiver only)
+    buffer: Buffer<T>,
+    // Waker registered by poll_recv()
+    token: Option<usize>,
 }
 
-impl<T> Receiver<T> {
+// Receiver's own buffer. Only locked by clone() to hand the values back to the channel, and by
+// the methods taking &self: the receiving methods get to it without locking.
+struct Buffer<T>(std::sync::Mutex<VecDeque<T>>);
+
+impl<T> Buffer<T> {
+    fn new() -> Self {
+        Buffer(std::sync::Mutex::new(VecDeque::new()))
+    }
+
+    fn get_mut(&mut self) -> &mut VecDeque<T> {
+        self.0.get_mut().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<T>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+// Values are never pinned, only moved in and out of the channel
+impl<T, F: ChannelFlavor<T>> Unpin for Sender<T, F> {}
+impl<T, F: ChannelFlavor<T>> Unpin for Receiver<T, F> {}
+
+impl<T, F: ChannelFlavor<T>> Receiver<T, F> {
     pub fn recv(&mut self) -> Option<T> {
-        if !self.buffer.is_empty() {
-            return self.buffer.pop_front();
-        }
-        let mut inner = self.shared.inner.lock().unwrap();
-        loop {
-            match inner.queue.pop_front() {
-                Some(t) => {
-                    std::mem::swap(&mut self.buffer, &mut inner.queue);
-                    return Some(t);
-                }
-                None if inner.senders == 0 => return None,
-                None => inner = self.shared.available.wait(inner).unwrap(),
-            }
+        let t = match self.buffer.get_mut().pop_front() {
+            Some(t) => Some(t),
+            None => self.flavor.recv(self.buffer.get_mut()),
+        };
+        t.inspect(|_| self.flavor.counters().on_recv(1))
+    }
+
+    /// Does not block: fails with `Empty` if no value is waiting in the channel.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let res = match self.buffer.get_mut().pop_front() {
+            Some(t) => Ok(t),
+            None => self.flavor.try_recv(self.buffer.get_mut()),
+        };
+        res.inspect(|_| self.flavor.counters().on_recv(1))
+    }
+
+    /// Blocking iterator over the values, ends when all the senders are gone.
+    pub fn iter(&mut self) -> Iter<'_, T, F> {
+        Iter { rx: self }
+    }
+
+    /// Non-blocking iterator over the values already waiting in the channel.
+    pub fn try_iter(&mut self) -> TryIter<'_, T, F> {
+        TryIter { rx: self }
+    }
+
+    /// Blocks for at most `timeout` waiting for a value.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        if let Some(t) = self.buffer.get_mut().pop_front() {
+            self.flavor.counters().on_recv(1);
+            return Ok(t);
+        }
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self
+                .flavor
+                .recv_deadline(self.buffer.get_mut(), deadline)
+                .inspect(|_| self.flavor.counters().on_recv(1)),
+            // Too far to be represented: no deadline
+            None => self.recv().ok_or(RecvTimeoutError::Disconnected),
+        }
+    }
+
+    /// Does not block: moves all the values waiting in the channel to `out`, taking the lock once.
+    /// Returns how many values were moved.
+    pub fn drain_into(&mut self, out: &mut Vec<T>) -> usize {
+        let buffer = self.buffer.get_mut();
+        let buffered = buffer.len();
+        out.extend(buffer.drain(..));
+        let len = buffered + self.flavor.drain_into(out);
+        self.flavor.counters().on_recv(len);
+        len
+    }
+
+    /// Number of values waiting in the channel, including the ones in this receiver's own buffer.
+    pub fn len(&self) -> usize {
+        self.buffer.lock().len() + self.flavor.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn sender_count(&self) -> usize {
+        self.flavor.senders()
+    }
+
+    /// The channel was closed or all the senders are gone: no more values will be sent,
+    /// the ones left can still be received.
+    pub fn is_closed(&self) -> bool {
+        self.flavor.is_disconnected()
+    }
+
+    /// Closes the channel for all the handles: sending fails from now on,
+    /// the values already sent can still be received.
+    pub fn close(&self) {
+        self.flavor.close()
+    }
+
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> Stats {
+        self.flavor.counters().stats()
+    }
+}
+
+pub struct Iter<'a, T, F: ChannelFlavor<T> = MutexFlavor<T>> {
+    rx: &'a mut Receiver<T, F>,
+}
+
+impl<'a, T, F: ChannelFlavor<T>> Iterator for Iter<'a, T, F> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.rx.recv()
+    }
+}
+
+pub struct TryIter<'a, T, F: ChannelFlavor<T> = MutexFlavor<T>> {
+    rx: &'a mut Receiver<T, F>,
+}
+
+impl<'a, T, F: ChannelFlavor<T>> Iterator for TryIter<'a, T, F> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        // Values stolen by the receiver are served from its buffer without locking
+        self.rx.try_recv().ok()
+    }
+}
+
+pub struct IntoIter<T, F: ChannelFlavor<T> = MutexFlavor<T>> {
+    rx: Receiver<T, F>,
+}
+
+impl<T, F: ChannelFlavor<T>> Iterator for IntoIter<T, F> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.rx.recv()
+    }
+}
+
+impl<T, F: ChannelFlavor<T>> IntoIterator for Receiver<T, F> {
+    type Item = T;
+    type IntoIter = IntoIter<T, F>;
+    fn into_iter(self) -> IntoIter<T, F> {
+        IntoIter { rx: self }
+    }
+}
+
+impl<'a, T, F: ChannelFlavor<T>> IntoIterator for &'a mut Receiver<T, F> {
+    type Item = T;
+    type IntoIter = Iter<'a, T, F>;
+    fn into_iter(self) -> Iter<'a, T, F> {
+        self.iter()
+    }
+}
+
+impl<T, F: ChannelFlavor<T>> Clone for Receiver<T, F> {
+    /// Receivers of a same channel compete for the values: each one is received only once.
+    fn clone(&self) -> Self {
+        self.flavor.add_receiver(&mut self.buffer.lock());
+        Self {
+            flavor: Arc::clone(&self.flavor),
+            buffer: Buffer::new(),
+            token: None,
         }
     }
 }
 
+impl<T, F: ChannelFlavor<T>> Drop for Receiver<T, F> {
+    fn drop(&mut self) {
+        self.unwatch();
+        self.flavor.drop_receiver(self.buffer.get_mut());
+    }
+}
+
+/// Asynchronous channel: send() never blocks.
 pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
-    let inner = Inner {
-        queue: VecDeque::new(),
-        senders: 1,
-    };
-    let shared = Shared {
-        inner: Mutex::new(inner),
-        available: Condvar::new(),
-    };
-    let shared = Arc::new(shared);
-    let tx = Sender {
-        shared: Arc::clone(&shared),
-    };
-    let rx = Receiver {
-        shared: Arc::clone(&shared),
-        buffer: VecDeque::default(),
-    };
-    (tx, rx)
+    with_capacity(None)
+}
+
+/// Synchronous channel: send() blocks while `capacity` messages are waiting in the queue.
+/// With `capacity == 0` (rendezvous), send() blocks until recv() has taken the value.
+pub fn sync_channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    with_capacity(Some(capacity))
+}
+
+/// Asynchronous channel over a lock-free queue: send() never blocks, and only takes a lock to
+/// unpark a waiting receiver. The receivers take a lock to pop values, one at a time.
+pub fn list_channel<T>() -> (Sender<T, ListFlavor<T>>, Receiver<T, ListFlavor<T>>) {
+    from_flavor(lockfree::Shared::new())
+}
+
+/// Same as [`list_channel`], allocating values by blocks rather than one at a time.
+pub fn block_channel<T>() -> (Sender<T, BlockFlavor<T>>, Receiver<T, BlockFlavor<T>>) {
+    from_flavor(lockfree::Shared::new())
+}
+
+fn with_capacity<T>(capacity: Option<usize>) -> (Sender<T>, Receiver<T>) {
+    from_flavor(mutex::Shared::new(capacity))
+}
+
+fn from_flavor<T, F: ChannelFlavor<T>>(flavor: F) -> (Sender<T, F>, Receiver<T, F>) {
+    let flavor = Arc::new(flavor);
+    (Sender::new(Arc::clone(&flavor)), Receiver::new(flavor))
+}
+
+impl<T, F: ChannelFlavor<T>> Sender<T, F> {
+    // The flavor must already count this sender
+    pub(crate) fn new(flavor: Arc<F>) -> Self {
+        Sender {
+            flavor,
+            #[cfg(feature = "futures")]
+            sink: None,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T, F: ChannelFlavor<T>> Receiver<T, F> {
+    // The flavor must already count this receiver
+    pub(crate) fn new(flavor: Arc<F>) -> Self {
+        Receiver {
+            flavor,
+            buffer: Buffer::new(),
+            token: None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -119,9 +415,9 @@ mod tests {
     #[test]
     fn ping_pong() {
         let (mut tx, mut rx) = channel();
-        tx.send(42);
-        tx.send(7);
-        tx.send(12);
+        tx.send(42).unwrap();
+        tx.send(7).unwrap();
+        tx.send(12).unwrap();
         assert_eq!(rx.recv(), Some(42));
         assert_eq!(rx.recv(), Some(7));
         assert_eq!(rx.recv(), Some(12));
@@ -140,6 +436,566 @@ mod tests {
         let (mut tx, rx) = channel();
         // Drop the only receiver
         drop(rx);
-        tx.send(12)
+        assert_eq!(tx.send(12), Err(SendError(12)));
+    }
+
+    #[test]
+    fn bounded_ping_pong() {
+        let (mut tx, mut rx) = sync_channel(2);
+        tx.send(42).unwrap();
+        tx.send(7).unwrap();
+        assert_eq!(rx.recv(), Some(42));
+        tx.send(12).unwrap();
+        assert_eq!(rx.recv(), Some(7));
+        assert_eq!(rx.recv(), Some(12));
+    }
+
+    #[test]
+    fn bounded_full_blocks() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::thread;
+        use std::time::Duration;
+
+        let (mut tx, mut rx) = sync_channel(1);
+        tx.send(1).unwrap();
+        let sent = Arc::new(AtomicBool::new(false));
+        let sent_t = Arc::clone(&sent);
+        let t = thread::spawn(move || {
+            // Queue is full: blocks until the receiver makes room
+            tx.send(2).unwrap();
+            sent_t.store(true, Ordering::SeqCst);
+        });
+        thread::sleep(Duration::from_millis(50));
+        assert!(!sent.load(Ordering::SeqCst));
+        // Draining the queue wakes up the sender
+        assert_eq!(rx.recv(), Some(1));
+        t.join().unwrap();
+        assert!(sent.load(Ordering::SeqCst));
+        assert_eq!(rx.recv(), Some(2));
+        assert_eq!(rx.recv(), None);
+    }
+
+    #[test]
+    fn rendezvous_blocks_until_recv() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::thread;
+        use std::time::Duration;
+
+        let (mut tx, mut rx) = sync_channel(0);
+        let sent = Arc::new(AtomicBool::new(false));
+        let sent_t = Arc::clone(&sent);
+        let t = thread::spawn(move || {
+            // No receiver yet: blocks until the value is taken
+            tx.send(42).unwrap();
+            sent_t.store(true, Ordering::SeqCst);
+        });
+        thread::sleep(Duration::from_millis(50));
+        assert!(!sent.load(Ordering::SeqCst));
+        assert_eq!(rx.recv(), Some(42));
+        t.join().unwrap();
+        assert!(sent.load(Ordering::SeqCst));
+        assert_eq!(rx.recv(), None);
+    }
+
+    #[test]
+    fn rendezvous_many_senders() {
+        use std::thread;
+
+        let (tx, mut rx) = sync_channel(0);
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let mut tx = tx.clone();
+                thread::spawn(move || {
+                    for j in 0..10 {
+                        tx.send(i * 10 + j).unwrap();
+                    }
+                })
+            })
+            .collect();
+        drop(tx);
+        let mut received: Vec<_> = rx.iter().collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+        received.sort_unstable();
+        assert_eq!(received, (0..40).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn closed_rx_wakes_blocked_sender() {
+        use std::thread;
+        use std::time::Duration;
+
+        let (mut tx, rx) = sync_channel(1);
+        tx.send(1).unwrap();
+        let t = thread::spawn(move || tx.send(2));
+        thread::sleep(Duration::from_millis(50));
+        drop(rx);
+        assert_eq!(t.join().unwrap(), Err(SendError(2)));
+    }
+
+    #[test]
+    fn closed_rx_rendezvous() {
+        use std::thread;
+        use std::time::Duration;
+
+        let (mut tx, rx) = sync_channel(0);
+        let t = thread::spawn(move || tx.send(42));
+        thread::sleep(Duration::from_millis(50));
+        // The value was never taken so it is handed back
+        drop(rx);
+        assert_eq!(t.join().unwrap(), Err(SendError(42)));
+    }
+
+    #[test]
+    fn try_recv() {
+        let (mut tx, mut rx) = channel();
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        tx.send(42).unwrap();
+        tx.send(7).unwrap();
+        assert_eq!(rx.try_recv(), Ok(42));
+        drop(tx);
+        // Pending values are still delivered after the last sender is gone
+        assert_eq!(rx.try_recv(), Ok(7));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn try_recv_bounded() {
+        let (mut tx, mut rx) = sync_channel(1);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        tx.send(42).unwrap();
+        assert_eq!(rx.try_recv(), Ok(42));
+        // The slot was freed
+        tx.send(7).unwrap();
+        assert_eq!(rx.try_recv(), Ok(7));
+    }
+
+    #[test]
+    fn recv_timeout() {
+        use std::thread;
+
+        let (mut tx, mut rx) = channel();
+        let timeout = Duration::from_millis(20);
+        let start = Instant::now();
+        assert_eq!(rx.recv_timeout(timeout), Err(RecvTimeoutError::Timeout));
+        assert!(start.elapsed() >= timeout);
+
+        let t = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            tx.send(42).unwrap();
+        });
+        assert_eq!(rx.recv_timeout(Duration::from_secs(10)), Ok(42));
+        t.join().unwrap();
+        assert_eq!(
+            rx.recv_timeout(timeout),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+
+    #[test]
+    fn recv_timeout_max() {
+        use std::thread;
+
+        let (mut tx, mut rx) = channel();
+        let t = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            tx.send(42).unwrap();
+        });
+        // Overflows an Instant: blocks like recv
+        assert_eq!(rx.recv_timeout(Duration::MAX), Ok(42));
+        t.join().unwrap();
+        assert_eq!(
+            rx.recv_timeout(Duration::MAX),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+
+    #[test]
+    fn iter() {
+        use std::thread;
+
+        let (mut tx, mut rx) = channel();
+        let t = thread::spawn(move || {
+            for i in 0..10 {
+                tx.send(i).unwrap();
+            }
+        });
+        // Ends once the sender is dropped
+        assert_eq!(rx.iter().collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+        t.join().unwrap();
+    }
+
+    #[test]
+    fn try_iter() {
+        let (mut tx, mut rx) = channel();
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        assert_eq!(rx.recv(), Some(1));
+        tx.send(3).unwrap();
+        // Drains both the receiver buffer and the shared queue without blocking
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(rx.try_iter().next(), None);
+        tx.send(4).unwrap();
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![4]);
+    }
+
+    #[test]
+    fn into_iter() {
+        let (mut tx, rx) = sync_channel(4);
+        for i in 0..4 {
+            tx.send(i).unwrap();
+        }
+        drop(tx);
+        let mut sum = 0;
+        for i in rx {
+            sum += i;
+        }
+        assert_eq!(sum, 6);
+    }
+
+    fn fan_out<F>((mut tx, rx): (Sender<usize, F>, Receiver<usize, F>))
+    where
+        F: ChannelFlavor<usize> + Send + Sync + 'static,
+    {
+        use std::thread;
+
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let rx = rx.clone();
+                thread::spawn(move || rx.into_iter().collect::<Vec<_>>())
+            })
+            .collect();
+        drop(rx);
+        for i in 0..1000 {
+            tx.send(i).unwrap();
+        }
+        drop(tx);
+        let mut received: Vec<_> = workers
+            .into_iter()
+            .flat_map(|w| w.join().unwrap())
+            .collect();
+        received.sort_unstable();
+        assert_eq!(received, (0..1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn mpmc() {
+        fan_out(channel());
+        fan_out(sync_channel(4));
+        fan_out(sync_channel(0));
+        fan_out(list_channel());
+        fan_out(block_channel());
+    }
+
+    #[test]
+    fn mpmc_closed_rx() {
+        fn check<F: ChannelFlavor<i32>>((mut tx, rx): (Sender<i32, F>, Receiver<i32, F>)) {
+            let rx2 = rx.clone();
+            drop(rx);
+            // One receiver is still alive
+            assert_eq!(tx.send(1), Ok(()));
+            drop(rx2);
+            assert_eq!(tx.send(2), Err(SendError(2)));
+        }
+        check(channel());
+        check(sync_channel(1));
+        check(list_channel());
+        check(block_channel());
+    }
+
+    #[test]
+    fn mpmc_dropped_receiver_buffer() {
+        let (mut tx, mut rx) = channel();
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        tx.send(3).unwrap();
+        // The single receiver steals 2 and 3 in its buffer
+        assert_eq!(rx.recv(), Some(1));
+        let mut rx2 = rx.clone();
+        // Values buffered by a dropped receiver are handed back to the others
+        drop(rx);
+        assert_eq!(rx2.try_recv(), Ok(2));
+        assert_eq!(rx2.try_recv(), Ok(3));
+    }
+
+    #[test]
+    fn mpmc_cloned_receiver_buffer() {
+        let (mut tx, mut rx) = channel();
+        tx.send_all(0..100).unwrap();
+        // The single receiver steals 1..100 in its buffer
+        assert_eq!(rx.recv(), Some(0));
+        let mut rx2 = rx.clone();
+        // Values buffered before the clone are handed back to the shared queue
+        assert_eq!(rx.len(), 99);
+        assert_eq!(rx2.len(), 99);
+        assert_eq!(rx2.recv_timeout(Duration::from_millis(300)), Ok(1));
+        assert_eq!(rx.recv(), Some(2));
+        assert_eq!(rx.len(), 97);
+    }
+
+    #[test]
+    fn send_all_drain_into() {
+        fn check<F: ChannelFlavor<i32>>((mut tx, mut rx): (Sender<i32, F>, Receiver<i32, F>)) {
+            tx.send_all(0..5).unwrap();
+            assert_eq!(rx.recv(), Some(0));
+            tx.send(5).unwrap();
+            let mut out = Vec::new();
+            assert_eq!(rx.drain_into(&mut out), 5);
+            assert_eq!(out, [1, 2, 3, 4, 5]);
+            assert_eq!(rx.drain_into(&mut out), 0);
+            drop(rx);
+            assert_eq!(tx.send_all([6, 7]), Err(SendError(vec![6, 7])));
+        }
+        check(channel());
+        check(sync_channel(8));
+        check(list_channel());
+        check(block_channel());
+    }
+
+    #[test]
+    fn introspection() {
+        fn check<F: ChannelFlavor<i32>>((mut tx, mut rx): (Sender<i32, F>, Receiver<i32, F>)) {
+            assert!(rx.is_empty());
+            tx.send_all(0..40).unwrap();
+            assert_eq!(tx.len(), 40);
+            assert_eq!(rx.recv(), Some(0));
+            // Including the values in the receiver's own buffer
+            assert_eq!(rx.len(), 39);
+            let tx2 = tx.clone();
+            assert_eq!(rx.sender_count(), 2);
+            drop(tx);
+            assert!(!rx.is_closed());
+            drop(tx2);
+            assert!(rx.is_closed());
+            assert_eq!(rx.recv(), Some(1));
+        }
+        check(channel());
+        check(sync_channel(64));
+        check(list_channel());
+        check(block_channel());
+    }
+
+    #[test]
+    fn closed_once_receivers_gone() {
+        fn check<F: ChannelFlavor<i32>>((tx, rx): (Sender<i32, F>, Receiver<i32, F>)) {
+            let rx2 = rx.clone();
+            drop(rx);
+            assert!(!tx.is_closed());
+            drop(rx2);
+            assert!(tx.is_closed());
+        }
+        check(channel());
+        check(sync_channel(64));
+        check(list_channel());
+        check(block_channel());
+    }
+
+    #[test]
+    fn close() {
+        fn check<F: ChannelFlavor<i32>>((mut tx, mut rx): (Sender<i32, F>, Receiver<i32, F>)) {
+            let mut tx2 = tx.clone();
+            tx.send(1).unwrap();
+            tx.send(2).unwrap();
+            rx.close();
+            assert!(tx.is_closed());
+            assert_eq!(tx2.send(3), Err(SendError(3)));
+            // The values sent before are still received
+            assert_eq!(rx.recv(), Some(1));
+            assert_eq!(rx.try_recv(), Ok(2));
+            assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+            assert_eq!(rx.recv(), None);
+        }
+        check(channel());
+        check(sync_channel(4));
+        check(list_channel());
+        check(block_channel());
+    }
+
+    #[test]
+    fn close_wakes_blocked() {
+        use std::thread;
+
+        fn check<F>((tx, mut rx): (Sender<i32, F>, Receiver<i32, F>))
+        where
+            F: ChannelFlavor<i32> + Send + Sync + 'static,
+        {
+            let t = thread::spawn(move || rx.recv());
+            thread::sleep(Duration::from_millis(20));
+            tx.close();
+            assert_eq!(t.join().unwrap(), None::<i32>);
+        }
+        check(channel());
+        check(list_channel());
+        check(block_channel());
+        let (mut tx, rx) = sync_channel(1);
+        tx.send(1).unwrap();
+        let t = thread::spawn(move || tx.send(2));
+        thread::sleep(Duration::from_millis(20));
+        rx.close();
+        assert_eq!(t.join().unwrap(), Err(SendError(2)));
+    }
+
+    #[cfg(feature = "stats")]
+    #[test]
+    fn stats() {
+        use std::thread;
+
+        let (mut tx, mut rx) = sync_channel(2);
+        tx.send_all([1, 2]).unwrap();
+        assert_eq!(rx.recv(), Some(1));
+        let t = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            tx.send(3).unwrap();
+            tx.stats()
+        });
+        assert_eq!(rx.recv(), Some(2));
+        // Blocked until the value is sent
+        assert_eq!(rx.recv(), Some(3));
+        let stats = t.join().unwrap();
+        assert_eq!(stats.sent, 3);
+        assert_eq!(rx.stats().received, 3);
+        assert_eq!(stats.max_len, 2);
+        assert!(rx.stats().blocked >= Duration::from_millis(10));
+
+        let (mut tx, mut rx) = list_channel();
+        tx.send_all(0..3).unwrap();
+        assert_eq!(rx.recv(), Some(0));
+        tx.send(3).unwrap();
+        let mut out = Vec::new();
+        rx.drain_into(&mut out);
+        tx.send(4).unwrap();
+        assert_eq!(tx.stats().max_len, 3);
+    }
+
+    #[test]
+    fn send_all_bounded() {
+        use std::thread;
+
+        let (mut tx, mut rx) = sync_channel(2);
+        // Waits for the receiver to make room
+        let t = thread::spawn(move || tx.send_all(0..10));
+        assert_eq!(rx.iter().collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+        assert_eq!(t.join().unwrap(), Ok(()));
+
+        // Fills up the queue at once without waiting
+        let (mut tx, mut rx) = sync_channel(4);
+        tx.send_all(0..4).unwrap();
+        assert_eq!(rx.len(), 4);
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [0, 1, 2, 3]);
+
+        // The receiver leaves while the sender waits for room: the rest is handed back
+        let (mut tx, mut rx) = sync_channel(2);
+        let t = thread::spawn(move || tx.send_all(0..10));
+        assert_eq!(rx.recv(), Some(0));
+        drop(rx);
+        let SendError(rest) = t.join().unwrap().unwrap_err();
+        assert!(rest.len() >= 6);
+        assert_eq!(rest, (10 - rest.len() as i32..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn try_send() {
+        let (mut tx, mut rx) = sync_channel(1);
+        assert_eq!(tx.try_send(1), Ok(()));
+        assert_eq!(tx.try_send(2), Err(TrySendError::Full(2)));
+        assert_eq!(rx.recv(), Some(1));
+        assert_eq!(tx.try_send(3), Ok(()));
+        drop(rx);
+        assert_eq!(tx.try_send(4), Err(TrySendError::Disconnected(4)));
+
+        // Unbounded: never full
+        let (mut tx, mut rx) = list_channel();
+        tx.try_send(1).unwrap();
+        tx.try_send(2).unwrap();
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [1, 2]);
+    }
+
+    #[test]
+    fn try_send_rendezvous() {
+        use std::thread;
+
+        let (mut tx, mut rx) = sync_channel(0);
+        // No receiver waiting
+        assert_eq!(tx.try_send(1), Err(TrySendError::Full(1)));
+        let t = thread::spawn(move || rx.recv());
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(tx.try_send(2), Ok(()));
+        assert_eq!(t.join().unwrap(), Some(2));
+    }
+
+    #[test]
+    fn send_timeout() {
+        use std::thread;
+
+        let (mut tx, mut rx) = sync_channel(1);
+        tx.send(1).unwrap();
+        // The receiver stalls: give up, getting the value back
+        let timeout = Duration::from_millis(20);
+        assert_eq!(
+            tx.send_timeout(2, timeout),
+            Err(SendTimeoutError::Timeout(2))
+        );
+        let t = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            rx.iter().collect::<Vec<_>>()
+        });
+        // Room is made in time
+        tx.send_timeout(3, Duration::from_secs(10)).unwrap();
+        drop(tx);
+        assert_eq!(t.join().unwrap(), [1, 3]);
+
+        let (mut tx, rx) = sync_channel(1);
+        drop(rx);
+        assert_eq!(
+            tx.send_timeout(1, timeout),
+            Err(SendTimeoutError::Disconnected(1))
+        );
+    }
+
+    #[test]
+    fn send_timeout_rendezvous() {
+        let (mut tx, mut rx) = sync_channel(0);
+        let timeout = Duration::from_millis(20);
+        assert_eq!(
+            tx.send_timeout(1, timeout),
+            Err(SendTimeoutError::Timeout(1))
+        );
+        // The value was taken back: nothing left to receive
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn send_timeout_rendezvous_blocked_sender() {
+        use std::thread;
+
+        let (mut tx, mut rx) = sync_channel(0);
+        let mut tx2 = tx.clone();
+        let t = thread::spawn(move || tx.send_timeout(1, Duration::from_millis(100)));
+        // Blocked on the slot taken by the first value
+        thread::sleep(Duration::from_millis(20));
+        let t2 = thread::spawn(move || tx2.send(2));
+        assert_eq!(t.join().unwrap(), Err(SendTimeoutError::Timeout(1)));
+        // Woken up once the slot was freed
+        assert_eq!(rx.recv_timeout(Duration::from_secs(2)), Ok(2));
+        assert_eq!(t2.join().unwrap(), Ok(()));
+    }
+
+    #[test]
+    fn send_timeout_max() {
+        use std::thread;
+
+        let (mut tx, mut rx) = sync_channel(0);
+        let t = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            rx.recv()
+        });
+        // Overflows an Instant: blocks like send
+        tx.send_timeout(1, Duration::MAX).unwrap();
+        assert_eq!(t.join().unwrap(), Some(1));
+        assert_eq!(
+            tx.send_timeout(2, Duration::MAX),
+            Err(SendTimeoutError::Disconnected(2))
+        );
     }
 }
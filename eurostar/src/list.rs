@@ -0,0 +1,175 @@
+use crate::sync::atomic::{AtomicPtr, Ordering};
+use crate::sync::thread;
+use std::cell::UnsafeCell;
+use std::ptr;
+
+// Atomic linked list of T, one allocation per value.
+
+struct Node<T> {
+    next: AtomicPtr<Node<T>>,
+    value: Option<T>,
+}
+
+impl<T> Node<T> {
+    fn new(value: Option<T>) -> *mut Self {
+        Box::into_raw(Box::new(Node {
+            next: AtomicPtr::new(ptr::null_mut()),
+            value,
+        }))
+    }
+}
+
+// Dmitry Vyukov's intrusive MPSC queue.
+// Senders swap themselves in at the head, the single consumer follows the `next` links from the tail.
+// The tail always points to a stub node whose value was already taken.
+pub struct Queue<T> {
+    head: AtomicPtr<Node<T>>,
+    tail: UnsafeCell<*mut Node<T>>,
+}
+
+impl<T> crate::lockfree::Queue for Queue<T> {
+    type Item = T;
+
+    fn new() -> Self {
+        let stub = Node::new(None);
+        Queue {
+            head: AtomicPtr::new(stub),
+            tail: UnsafeCell::new(stub),
+        }
+    }
+
+    fn push(&self, t: T) {
+        let node = Node::new(Some(t));
+        let prev = self.head.swap(node, Ordering::AcqRel);
+        // SAFETY: prev cannot be freed by the consumer until its next link is set
+        unsafe { (*prev).next.store(node, Ordering::Release) }
+    }
+
+    unsafe fn pop(&self) -> Option<T> {
+        loop {
+            let tail = *self.tail.get();
+            let next = (*tail).next.load(Ordering::Acquire);
+            if !next.is_null() {
+                // next becomes the new stub, the old one can be freed
+                *self.tail.get() = next;
+                let t = (*next).value.take();
+                drop(Box::from_raw(tail));
+                return t;
+            }
+            if self.head.load(Ordering::Acquire) == tail {
+                return None;
+            }
+            // A sender swapped the head but did not link its node yet, it is about to
+            thread::yield_now();
+        }
+    }
+
+    unsafe fn is_empty(&self) -> bool {
+        let tail = *self.tail.get();
+        (*tail).next.load(Ordering::Acquire).is_null() && self.head.load(Ordering::Acquire) == tail
+    }
+
+    unsafe fn len(&self) -> usize {
+        // Count the linked nodes after the stub, a node swapped in but not linked yet is not counted
+        let mut len = 0;
+        let mut node = (*(*self.tail.get())).next.load(Ordering::Acquire);
+        while !node.is_null() {
+            len += 1;
+            node = (*node).next.load(Ordering::Acquire);
+        }
+        len
+    }
+}
+
+impl<T> Drop for Queue<T> {
+    fn drop(&mut self) {
+        let mut node = *self.tail.get_mut();
+        while !node.is_null() {
+            // SAFETY: we have exclusive access, every node was allocated by Node::new
+            let boxed = unsafe { Box::from_raw(node) };
+            node = boxed.next.load(Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{list_channel, SendError, TryRecvError};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn ping_pong() {
+        let (mut tx, mut rx) = list_channel();
+        tx.send(42).unwrap();
+        tx.send(7).unwrap();
+        tx.send(12).unwrap();
+        assert_eq!(rx.recv(), Some(42));
+        assert_eq!(rx.recv(), Some(7));
+        assert_eq!(rx.recv(), Some(12));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn closed_tx() {
+        let (tx, mut rx) = list_channel::<()>();
+        drop(tx);
+        assert_eq!(rx.recv(), None);
+    }
+
+    #[test]
+    fn closed_rx() {
+        let (mut tx, rx) = list_channel();
+        drop(rx);
+        assert_eq!(tx.send(12), Err(SendError(12)));
+    }
+
+    #[test]
+    fn drop_pending_values() {
+        use std::sync::Arc;
+
+        let value = Arc::new(());
+        let (mut tx, rx) = list_channel();
+        tx.send(Arc::clone(&value)).unwrap();
+        tx.send(Arc::clone(&value)).unwrap();
+        drop(rx);
+        drop(tx);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn wakes_parked_receiver() {
+        let (mut tx, mut rx) = list_channel();
+        let t = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            tx.send(42).unwrap();
+            thread::sleep(Duration::from_millis(50));
+        });
+        assert_eq!(rx.recv(), Some(42));
+        // Woken up again when the sender is dropped
+        assert_eq!(rx.recv(), None);
+        t.join().unwrap();
+    }
+
+    #[test]
+    fn many_senders() {
+        let (tx, mut rx) = list_channel();
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let mut tx = tx.clone();
+                thread::spawn(move || {
+                    for j in 0..1000 {
+                        tx.send(i * 1000 + j).unwrap();
+                    }
+                })
+            })
+            .collect();
+        drop(tx);
+        let mut received: Vec<_> = rx.iter().collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+        received.sort_unstable();
+        assert_eq!(received, (0..8000).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,299 @@
+use crate::select::Selectable;
+use crate::stats::Counters;
+use crate::sync::atomic::{self, AtomicBool, AtomicUsize, Ordering};
+use crate::sync::thread::{self, Thread};
+use crate::sync::{self, Mutex};
+use crate::waker::Wakers;
+use crate::{ChannelFlavor, RecvTimeoutError, SendError, TryRecvError};
+use std::collections::VecDeque;
+use std::task::Waker;
+use std::time::Instant;
+
+// Unbounded flavors over a lock-free queue: atomic queue + thread::park.
+// Senders only take a lock to unpark a waiting receiver. The queue has a single consumer,
+// so the receivers take turns through a lock, and park themselves when the queue is empty.
+
+// Multi-producer single-consumer atomic queue.
+pub trait Queue {
+    type Item;
+
+    fn new() -> Self;
+
+    fn push(&self, t: Self::Item);
+
+    /// SAFETY: must only be called by one consumer at a time.
+    unsafe fn pop(&self) -> Option<Self::Item>;
+
+    /// SAFETY: must only be called by one consumer at a time.
+    unsafe fn is_empty(&self) -> bool;
+
+    /// SAFETY: must only be called by one consumer at a time.
+    unsafe fn len(&self) -> usize;
+}
+
+pub struct Shared<Q> {
+    queue: Q,
+    // Serializes the receivers, the queue only supports one consumer at a time
+    consumer: Mutex<()>,
+    senders: AtomicUsize,
+    receivers: AtomicUsize,
+    // Closed explicitly by close(), while handles are still alive
+    closed: AtomicBool,
+    // Receiver threads registered while they are about to park
+    waiters: Mutex<Vec<Thread>>,
+    // Number of registered waiters, checked by senders without taking the lock
+    waiting: AtomicUsize,
+    // Receivers watched by a Select
+    wakers: Wakers,
+    counters: Counters,
+}
+
+// SAFETY: values are moved across threads through the queue, the consumer side is only
+// ever accessed with the consumer lock held
+unsafe impl<Q: Queue> Send for Shared<Q> where Q::Item: Send {}
+unsafe impl<Q: Queue> Sync for Shared<Q> where Q::Item: Send {}
+
+impl<Q: Queue> Shared<Q> {
+    pub(crate) fn new() -> Self {
+        Shared {
+            queue: Q::new(),
+            consumer: Mutex::new(()),
+            senders: AtomicUsize::new(1),
+            receivers: AtomicUsize::new(1),
+            closed: AtomicBool::new(false),
+            waiters: Mutex::new(Vec::new()),
+            waiting: AtomicUsize::new(0),
+            wakers: Wakers::new(),
+            counters: Counters::default(),
+        }
+    }
+
+    // Registers the current thread as a receiver to unpark.
+    // Returns false, already unregistered, if there is no need to park as something happened in the meantime.
+    fn register(&self) -> bool {
+        let mut waiters = sync::lock(&self.waiters);
+        waiters.push(thread::current());
+        self.waiting.store(waiters.len(), Ordering::SeqCst);
+        drop(waiters);
+        // Pairs with the fence in wake_one(): either the sender sees the waiter, or we see its value
+        atomic::fence(Ordering::SeqCst);
+        let consumer = sync::lock(&self.consumer);
+        // SAFETY: the consumer lock is held
+        let empty = unsafe { self.queue.is_empty() };
+        drop(consumer);
+        if empty && !self.is_disconnected() {
+            return true;
+        }
+        self.unregister();
+        false
+    }
+
+    // Removes the current thread from the waiters, if a sender did not already
+    fn unregister(&self) {
+        let id = thread::current().id();
+        let mut waiters = sync::lock(&self.waiters);
+        waiters.retain(|thread| thread.id() != id);
+        self.waiting.store(waiters.len(), Ordering::SeqCst);
+    }
+
+    fn wake_one(&self) {
+        atomic::fence(Ordering::SeqCst);
+        // Fast path: no receiver is parked, no lock is taken
+        if self.waiting.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        let mut waiters = sync::lock(&self.waiters);
+        let thread = waiters.pop();
+        self.waiting.store(waiters.len(), Ordering::SeqCst);
+        drop(waiters);
+        // Unparked with the lock released, so that the receiver does not wake up to a held lock
+        if let Some(thread) = thread {
+            thread.unpark();
+        }
+    }
+
+    fn wake_all(&self) {
+        atomic::fence(Ordering::SeqCst);
+        // Fast path: no receiver is parked, no lock is taken
+        if self.waiting.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        let mut waiters = sync::lock(&self.waiters);
+        let threads = std::mem::take(&mut *waiters);
+        self.waiting.store(0, Ordering::SeqCst);
+        drop(waiters);
+        for thread in threads {
+            thread.unpark();
+        }
+    }
+}
+
+impl<Q: Queue> ChannelFlavor<Q::Item> for Shared<Q> {
+    // Never blocks
+    type SendState = ();
+
+    fn send(&self, t: Q::Item) -> Result<(), SendError<Q::Item>> {
+        if self.is_closed() {
+            return Err(SendError(t));
+        }
+        self.counters.on_push(1);
+        self.queue.push(t);
+        self.wake_one();
+        self.wakers.notify();
+        Ok(())
+    }
+
+    // Pushes all the values, then notifies once
+    fn send_all(&self, values: VecDeque<Q::Item>) -> Result<(), SendError<VecDeque<Q::Item>>> {
+        if self.is_closed() {
+            return Err(SendError(values));
+        }
+        let len = values.len();
+        self.counters.on_push(len);
+        for t in values {
+            self.queue.push(t);
+        }
+        match len {
+            0 => return Ok(()),
+            1 => self.wake_one(),
+            _ => self.wake_all(),
+        }
+        self.wakers.notify();
+        Ok(())
+    }
+
+    fn add_sender(&self) {
+        self.senders.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn drop_sender(&self) {
+        // If it was the last sender, wake the receivers up so that they do not park forever
+        if self.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.wake_all();
+            self.wakers.notify();
+        }
+    }
+
+    fn add_receiver(&self, _buffer: &mut VecDeque<Q::Item>) {
+        self.receivers.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn drop_receiver(&self, _buffer: &mut VecDeque<Q::Item>) {
+        self.receivers.fetch_sub(1, Ordering::AcqRel);
+    }
+
+    fn try_recv(&self, _buffer: &mut VecDeque<Q::Item>) -> Result<Q::Item, TryRecvError> {
+        let _consumer = sync::lock(&self.consumer);
+        // SAFETY: the consumer lock is held
+        let t = match unsafe { self.queue.pop() } {
+            Some(t) => t,
+            None if self.is_disconnected() => {
+                // A value may have been pushed right before the last sender left
+                unsafe { self.queue.pop() }.ok_or(TryRecvError::Disconnected)?
+            }
+            None => return Err(TryRecvError::Empty),
+        };
+        self.counters.on_pop(1);
+        Ok(t)
+    }
+
+    fn drain_into(&self, out: &mut Vec<Q::Item>) -> usize {
+        let _consumer = sync::lock(&self.consumer);
+        let len = out.len();
+        // SAFETY: the consumer lock is held
+        while let Some(t) = unsafe { self.queue.pop() } {
+            out.push(t);
+        }
+        self.counters.on_pop(out.len() - len);
+        out.len() - len
+    }
+
+    fn recv(&self, buffer: &mut VecDeque<Q::Item>) -> Option<Q::Item> {
+        loop {
+            match self.try_recv(buffer) {
+                Ok(t) => return Some(t),
+                Err(TryRecvError::Disconnected) => return None,
+                Err(TryRecvError::Empty) => {
+                    if self.register() {
+                        let _blocking = self.counters.blocking();
+                        thread::park();
+                        self.unregister();
+                    }
+                }
+            }
+        }
+    }
+
+    fn recv_deadline(
+        &self,
+        buffer: &mut VecDeque<Q::Item>,
+        deadline: Instant,
+    ) -> Result<Q::Item, RecvTimeoutError> {
+        loop {
+            match self.try_recv(buffer) {
+                Ok(t) => return Ok(t),
+                Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+                Err(TryRecvError::Empty) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(RecvTimeoutError::Timeout);
+                    }
+                    if self.register() {
+                        let _blocking = self.counters.blocking();
+                        sync::park_timeout(deadline - now);
+                        self.unregister();
+                    }
+                }
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        let _consumer = sync::lock(&self.consumer);
+        // SAFETY: the consumer lock is held
+        unsafe { self.queue.len() }
+    }
+
+    fn senders(&self) -> usize {
+        self.senders.load(Ordering::Acquire)
+    }
+
+    // Sending fails
+    fn is_closed(&self) -> bool {
+        self.receivers.load(Ordering::Acquire) == 0 || self.closed.load(Ordering::SeqCst)
+    }
+
+    // No more values will be sent, the ones left can still be received
+    fn is_disconnected(&self) -> bool {
+        self.senders.load(Ordering::Acquire) == 0 || self.closed.load(Ordering::SeqCst)
+    }
+
+    fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        // Wake the receivers up so that they do not park forever
+        self.wake_all();
+        self.wakers.notify();
+    }
+
+    fn counters(&self) -> &Counters {
+        &self.counters
+    }
+}
+
+impl<Q: Queue> Selectable for Shared<Q> {
+    fn is_ready(&self) -> bool {
+        let consumer = sync::lock(&self.consumer);
+        // SAFETY: the consumer lock is held
+        let empty = unsafe { self.queue.is_empty() };
+        drop(consumer);
+        !empty || self.is_disconnected()
+    }
+
+    fn watch(&self, waker: &Waker) -> usize {
+        self.wakers.register(waker)
+    }
+
+    fn unwatch(&self, token: usize) {
+        self.wakers.unregister(token)
+    }
+}
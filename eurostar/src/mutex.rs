@@ -0,0 +1,663 @@
+use crate::select::Selectable;
+use crate::stats::Counters;
+use crate::sync::{self, Condvar, Mutex, MutexGuard};
+use crate::waker::Wakers;
+use crate::{
+    ChannelFlavor, RecvTimeoutError, SendError, SendTimeoutError, TryRecvError, TrySendError,
+};
+use std::collections::VecDeque;
+use std::ops::Deref;
+#[cfg(test)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll, Waker};
+use std::time::Instant;
+
+// Mutex + Condvar + VecDeque flavor, unbounded, bounded or rendezvous.
+#[derive(Default)]
+struct Inner<T> {
+    queue: VecDeque<T>,
+    senders: usize,
+    receivers: usize,
+    // None for unbounded channels, Some(0) for rendezvous channels
+    capacity: Option<usize>,
+    // Number of values taken by the receivers out of a bounded queue
+    taken: usize,
+    // Closed explicitly by close(), while handles are still alive
+    closed: bool,
+    // Receivers blocked on `available`, notified only if there are some
+    waiting_receivers: usize,
+    // Senders blocked on `space`, notified only if there are some
+    waiting_senders: usize,
+}
+
+impl<T> Inner<T> {
+    // Sending fails
+    fn is_closed(&self) -> bool {
+        self.receivers == 0 || self.closed
+    }
+
+    // No more values will be sent, the ones left can still be received
+    fn is_disconnected(&self) -> bool {
+        self.senders == 0 || self.closed
+    }
+
+    fn is_full(&self) -> bool {
+        match self.capacity {
+            // A rendezvous channel still holds the value being handed off
+            Some(capacity) => self.queue.len() >= capacity.max(1),
+            None => false,
+        }
+    }
+}
+
+// Condvar counting its notifications in tests, to check that the ones nobody waits for are skipped
+#[derive(Default)]
+struct Signal {
+    condvar: Condvar,
+    #[cfg(test)]
+    notified: AtomicUsize,
+}
+
+impl Signal {
+    fn notify_one(&self) {
+        #[cfg(test)]
+        self.notified.fetch_add(1, Ordering::Relaxed);
+        self.condvar.notify_one();
+    }
+
+    fn notify_all(&self) {
+        #[cfg(test)]
+        self.notified.fetch_add(1, Ordering::Relaxed);
+        self.condvar.notify_all();
+    }
+}
+
+// Waited on through the sync helpers
+impl Deref for Signal {
+    type Target = Condvar;
+
+    fn deref(&self) -> &Condvar {
+        &self.condvar
+    }
+}
+
+pub struct Shared<T> {
+    inner: Mutex<Inner<T>>,
+    available: Signal,
+    // Signaled when a receiver frees up a slot in a bounded queue
+    space: Signal,
+    // Notified along with `available`, for the receivers watched by a Select or a future
+    wakers: Wakers,
+    // Notified along with `space`, for the async senders
+    space_wakers: Wakers,
+    counters: Counters,
+}
+
+// Progress of an async send across polls
+#[derive(Default)]
+pub struct SendState {
+    // Rendezvous: the value was pushed, waiting for a receiver to take it
+    ticket: Option<usize>,
+    // Registered in `space_wakers`
+    token: Option<usize>,
+}
+
+impl<T> Shared<T> {
+    pub(crate) fn new(capacity: Option<usize>) -> Self {
+        let inner = Inner {
+            queue: VecDeque::new(),
+            senders: 1,
+            receivers: 1,
+            capacity,
+            taken: 0,
+            closed: false,
+            waiting_receivers: 0,
+            waiting_senders: 0,
+        };
+        Shared {
+            inner: Mutex::new(inner),
+            available: Signal::default(),
+            space: Signal::default(),
+            wakers: Wakers::new(),
+            space_wakers: Wakers::new(),
+            counters: Counters::default(),
+        }
+    }
+
+    // Blocks while the queue is full, then for the hand-off on rendezvous channels.
+    // Gives up at the deadline, if any, handing the value back.
+    fn send_until(&self, t: T, deadline: Option<Instant>) -> Result<(), SendTimeoutError<T>> {
+        // Acquire lock
+        let mut inner = sync::lock(&self.inner);
+        // Wait for a receiver to make room (bounded channels only)
+        while !inner.is_closed() && inner.is_full() {
+            inner = match self.wait_space(inner, deadline) {
+                Ok(inner) => inner,
+                Err(_) => return Err(SendTimeoutError::Timeout(t)),
+            };
+        }
+        if inner.is_closed() {
+            return Err(SendTimeoutError::Disconnected(t));
+        }
+        inner.queue.push_back(t);
+        self.counters.on_len(inner.queue.len());
+        if inner.capacity == Some(0) {
+            // Rendezvous: wait for a receiver to take the value
+            let ticket = inner.taken + inner.queue.len();
+            let waiting = inner.waiting_receivers > 0;
+            // Release lock: a Waker may run inline and use the channel
+            drop(inner);
+            if waiting {
+                self.available.notify_one();
+            }
+            self.wakers.notify();
+            // Acquire lock
+            inner = sync::lock(&self.inner);
+            while inner.taken < ticket {
+                if inner.is_closed() {
+                    // The receivers left without taking the value, which is the only one in the queue
+                    let t = inner.queue.pop_back().expect("value being handed off");
+                    return Err(SendTimeoutError::Disconnected(t));
+                }
+                inner = match self.wait_space(inner, deadline) {
+                    Ok(inner) => inner,
+                    Err(mut inner) => {
+                        // Nobody took the value in time, take it back
+                        let t = inner.queue.pop_back().expect("value being handed off");
+                        let waiting = inner.waiting_senders > 0;
+                        // Release lock
+                        drop(inner);
+                        // The slot is free again: notify a sender blocked on it
+                        if waiting {
+                            self.space.notify_one();
+                        }
+                        self.space_wakers.notify();
+                        return Err(SendTimeoutError::Timeout(t));
+                    }
+                };
+            }
+            return Ok(());
+        }
+        let waiting = inner.waiting_receivers > 0;
+        // Release lock
+        drop(inner);
+        // Notify blocked thread, if any
+        if waiting {
+            self.available.notify_one();
+        }
+        self.wakers.notify();
+        Ok(())
+    }
+
+    // Waits for a receiver to take a value, until the deadline if any.
+    // Returns the lock back as an error, without waiting, once the deadline passed.
+    fn wait_space<'a>(
+        &self,
+        mut inner: MutexGuard<'a, Inner<T>>,
+        deadline: Option<Instant>,
+    ) -> Result<MutexGuard<'a, Inner<T>>, MutexGuard<'a, Inner<T>>> {
+        let timeout = match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Err(inner);
+                }
+                Some(deadline - now)
+            }
+            None => None,
+        };
+        let _blocking = self.counters.blocking();
+        inner.waiting_senders += 1;
+        inner = match timeout {
+            Some(timeout) => sync::wait_timeout(&self.space, inner, timeout),
+            None => sync::wait(&self.space, inner),
+        };
+        inner.waiting_senders -= 1;
+        Ok(inner)
+    }
+
+    // Called with the lock held, right after a value was popped out of the shared queue
+    fn on_pop(&self, mut inner: MutexGuard<'_, Inner<T>>, buffer: &mut VecDeque<T>) {
+        if inner.capacity.is_none() {
+            // Unbounded with a single receiver: steal the whole queue to avoid taking the lock on the next calls.
+            // With several receivers, leave the values to the others.
+            if inner.receivers == 1 {
+                std::mem::swap(buffer, &mut inner.queue);
+            }
+            return;
+        }
+        // Bounded: do not steal the queue as it would double the capacity
+        inner.taken += 1;
+        let rendezvous = inner.capacity == Some(0);
+        let waiting = inner.waiting_senders > 0;
+        drop(inner);
+        if waiting && rendezvous {
+            // Notify the sender waiting on the hand-off as well as the ones waiting for the slot
+            self.space.notify_all();
+        } else if waiting {
+            // Notify a sender blocked on a full queue
+            self.space.notify_one();
+        }
+        self.space_wakers.notify();
+    }
+}
+
+impl<T> ChannelFlavor<T> for Shared<T> {
+    type SendState = SendState;
+
+    fn send(&self, t: T) -> Result<(), SendError<T>> {
+        self.send_until(t, None).map_err(|err| match err {
+            SendTimeoutError::Disconnected(t) => SendError(t),
+            // Never times out without a deadline
+            SendTimeoutError::Timeout(t) => SendError(t),
+        })
+    }
+
+    fn try_send(&self, t: T) -> Result<(), TrySendError<T>> {
+        // Acquire lock
+        let mut inner = sync::lock(&self.inner);
+        if inner.is_closed() {
+            return Err(TrySendError::Disconnected(t));
+        }
+        // Rendezvous: the value can only be handed off to a receiver already waiting for it
+        let rendezvous = inner.capacity == Some(0);
+        if inner.is_full() || (rendezvous && inner.waiting_receivers == 0) {
+            return Err(TrySendError::Full(t));
+        }
+        inner.queue.push_back(t);
+        self.counters.on_len(inner.queue.len());
+        let waiting = inner.waiting_receivers > 0;
+        // Release lock
+        drop(inner);
+        // Notify blocked thread, if any
+        if waiting {
+            self.available.notify_one();
+        }
+        self.wakers.notify();
+        Ok(())
+    }
+
+    fn send_deadline(&self, t: T, deadline: Instant) -> Result<(), SendTimeoutError<T>> {
+        self.send_until(t, Some(deadline))
+    }
+
+    // Takes the lock and notifies only once on unbounded channels. Bounded channels are filled up
+    // to their capacity at once, waiting for room only when full. The values not sent are handed back
+    // if the receivers leave in the meantime.
+    fn send_all(&self, mut values: VecDeque<T>) -> Result<(), SendError<VecDeque<T>>> {
+        // Acquire lock
+        let mut inner = sync::lock(&self.inner);
+        if inner.capacity == Some(0) {
+            // Rendezvous: one hand-off at a time
+            drop(inner);
+            while let Some(t) = values.pop_front() {
+                if let Err(SendError(t)) = self.send(t) {
+                    values.push_front(t);
+                    return Err(SendError(values));
+                }
+            }
+            return Ok(());
+        }
+        loop {
+            if inner.is_closed() {
+                return Err(SendError(values));
+            }
+            let room = match inner.capacity {
+                Some(capacity) => capacity.saturating_sub(inner.queue.len()),
+                None => values.len(),
+            };
+            let len = room.min(values.len());
+            if len == 0 && !values.is_empty() {
+                // Wait for a receiver to make room
+                inner = match self.wait_space(inner, None) {
+                    Ok(inner) | Err(inner) => inner,
+                };
+                continue;
+            }
+            inner.queue.extend(values.drain(..len));
+            self.counters.on_len(inner.queue.len());
+            let waiting = inner.waiting_receivers;
+            // Release lock
+            drop(inner);
+            // Notify blocked threads, if any
+            match len.min(waiting) {
+                0 => {}
+                1 => self.available.notify_one(),
+                _ => self.available.notify_all(),
+            }
+            if len > 0 {
+                self.wakers.notify();
+            }
+            if values.is_empty() {
+                return Ok(());
+            }
+            // Full: the receivers were notified before waiting for room
+            inner = sync::lock(&self.inner);
+        }
+    }
+
+    // Async send: waits for room without blocking the thread, then for the hand-off on rendezvous channels
+    fn poll_send(
+        &self,
+        cx: &mut Context<'_>,
+        value: &mut Option<T>,
+        state: &mut SendState,
+    ) -> Poll<Result<(), SendError<T>>> {
+        self.cancel_send(state);
+        // Acquire lock
+        let mut inner = sync::lock(&self.inner);
+        if let Some(ticket) = state.ticket {
+            if inner.taken >= ticket {
+                return Poll::Ready(Ok(()));
+            }
+            if inner.is_closed() {
+                // The receivers left without taking the value, which is the only one in the queue
+                let t = inner.queue.pop_back().expect("value being handed off");
+                return Poll::Ready(Err(SendError(t)));
+            }
+        } else {
+            let t = value.take().expect("send future polled after completion");
+            if inner.is_closed() {
+                return Poll::Ready(Err(SendError(t)));
+            }
+            if inner.is_full() {
+                *value = Some(t);
+            } else {
+                inner.queue.push_back(t);
+                self.counters.on_len(inner.queue.len());
+                let rendezvous = inner.capacity == Some(0);
+                if rendezvous {
+                    state.ticket = Some(inner.taken + inner.queue.len());
+                    // Registered with the lock held so that the hand-off cannot be missed
+                    state.token = Some(self.space_wakers.register(cx.waker()));
+                }
+                let waiting = inner.waiting_receivers > 0;
+                // Release lock
+                drop(inner);
+                // Notify blocked thread, if any
+                if waiting {
+                    self.available.notify_one();
+                }
+                self.wakers.notify();
+                return if rendezvous {
+                    Poll::Pending
+                } else {
+                    Poll::Ready(Ok(()))
+                };
+            }
+        }
+        // Registered with the lock held so that a receiver cannot free up a slot in the meantime
+        state.token = Some(self.space_wakers.register(cx.waker()));
+        Poll::Pending
+    }
+
+    fn cancel_send(&self, state: &mut SendState) {
+        if let Some(token) = state.token.take() {
+            self.space_wakers.unregister(token);
+        }
+    }
+
+    fn add_sender(&self) {
+        // Acquire lock
+        let mut inner = sync::lock(&self.inner);
+        // Increase senders counter
+        inner.senders += 1;
+    }
+
+    fn drop_sender(&self) {
+        // Acquire lock
+        let mut inner = sync::lock(&self.inner);
+        // Decrease senders counter
+        inner.senders -= 1;
+        let was_last = inner.senders == 0;
+        let waiting = inner.waiting_receivers > 0;
+        // Release lock
+        drop(inner);
+
+        // If it was the last sender, notify the receiver threads
+        // so that they don't hang waiting for a sender that does not exists
+        if was_last {
+            if waiting {
+                self.available.notify_all();
+            }
+            self.wakers.notify();
+        }
+    }
+
+    fn add_receiver(&self, buffer: &mut VecDeque<T>) {
+        // Acquire lock
+        let mut inner = sync::lock(&self.inner);
+        inner.receivers += 1;
+        if !buffer.is_empty() {
+            // The queue is no longer stolen: hand the stolen values back for the clone, in order
+            while let Some(t) = buffer.pop_back() {
+                inner.queue.push_front(t);
+            }
+            // Release lock
+            drop(inner);
+            self.available.notify_all();
+            self.wakers.notify();
+        }
+    }
+
+    fn recv(&self, buffer: &mut VecDeque<T>) -> Option<T> {
+        let mut inner = sync::lock(&self.inner);
+        loop {
+            match inner.queue.pop_front() {
+                Some(t) => {
+                    self.on_pop(inner, buffer);
+                    return Some(t);
+                }
+                None if inner.is_disconnected() => return None,
+                None => {
+                    let _blocking = self.counters.blocking();
+                    inner.waiting_receivers += 1;
+                    inner = sync::wait(&self.available, inner);
+                    inner.waiting_receivers -= 1;
+                }
+            }
+        }
+    }
+
+    fn try_recv(&self, buffer: &mut VecDeque<T>) -> Result<T, TryRecvError> {
+        let mut inner = sync::lock(&self.inner);
+        match inner.queue.pop_front() {
+            Some(t) => {
+                self.on_pop(inner, buffer);
+                Ok(t)
+            }
+            None if inner.is_disconnected() => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    fn drain_into(&self, out: &mut Vec<T>) -> usize {
+        let mut inner = sync::lock(&self.inner);
+        let len = inner.queue.len();
+        out.extend(inner.queue.drain(..));
+        if len == 0 || inner.capacity.is_none() {
+            return len;
+        }
+        inner.taken += len;
+        let waiting = inner.waiting_senders > 0;
+        drop(inner);
+        // Notify the senders waiting for room or for the hand-off
+        if waiting {
+            self.space.notify_all();
+        }
+        self.space_wakers.notify();
+        len
+    }
+
+    fn recv_deadline(
+        &self,
+        buffer: &mut VecDeque<T>,
+        deadline: Instant,
+    ) -> Result<T, RecvTimeoutError> {
+        let mut inner = sync::lock(&self.inner);
+        loop {
+            match inner.queue.pop_front() {
+                Some(t) => {
+                    self.on_pop(inner, buffer);
+                    return Ok(t);
+                }
+                None if inner.is_disconnected() => return Err(RecvTimeoutError::Disconnected),
+                None => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(RecvTimeoutError::Timeout);
+                    }
+                    let _blocking = self.counters.blocking();
+                    inner.waiting_receivers += 1;
+                    inner = sync::wait_timeout(&self.available, inner, deadline - now);
+                    inner.waiting_receivers -= 1;
+                }
+            }
+        }
+    }
+
+    fn drop_receiver(&self, buffer: &mut VecDeque<T>) {
+        // Acquire lock
+        let mut inner = sync::lock(&self.inner);
+        inner.receivers -= 1;
+        if inner.receivers == 0 {
+            // Release lock
+            drop(inner);
+            // Wake up all the senders blocked on a full queue so they can fail
+            self.space.notify_all();
+            self.space_wakers.notify();
+        } else if !buffer.is_empty() {
+            // Hand the stolen values back to the remaining receivers, in order
+            while let Some(t) = buffer.pop_back() {
+                inner.queue.push_front(t);
+            }
+            drop(inner);
+            self.available.notify_all();
+            self.wakers.notify();
+        }
+    }
+
+    fn len(&self) -> usize {
+        sync::lock(&self.inner).queue.len()
+    }
+
+    fn senders(&self) -> usize {
+        sync::lock(&self.inner).senders
+    }
+
+    fn is_closed(&self) -> bool {
+        sync::lock(&self.inner).is_closed()
+    }
+
+    fn is_disconnected(&self) -> bool {
+        sync::lock(&self.inner).is_disconnected()
+    }
+
+    fn close(&self) {
+        // Acquire lock
+        let mut inner = sync::lock(&self.inner);
+        inner.closed = true;
+        // Release lock
+        drop(inner);
+        // Wake everyone up: blocked senders fail, blocked receivers get the values left then None
+        self.available.notify_all();
+        self.space.notify_all();
+        self.wakers.notify();
+        self.space_wakers.notify();
+    }
+
+    fn counters(&self) -> &Counters {
+        &self.counters
+    }
+}
+
+impl<T> Selectable for Shared<T> {
+    fn is_ready(&self) -> bool {
+        let inner = sync::lock(&self.inner);
+        !inner.queue.is_empty() || inner.is_disconnected()
+    }
+
+    fn watch(&self, waker: &Waker) -> usize {
+        self.wakers.register(waker)
+    }
+
+    fn unwatch(&self, token: usize) {
+        self.wakers.unregister(token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{channel, sync_channel, Receiver};
+    use std::sync::atomic::Ordering;
+    use std::thread;
+    use std::time::Duration;
+
+    fn notified(rx: &Receiver<i32>) -> (usize, usize) {
+        let shared = &rx.flavor;
+        (
+            shared.available.notified.load(Ordering::Relaxed),
+            shared.space.notified.load(Ordering::Relaxed),
+        )
+    }
+
+    // Blocks until `n` receivers wait on the channel
+    fn wait_for_receivers(rx: &Receiver<i32>, n: usize) {
+        while rx.flavor.inner.lock().unwrap().waiting_receivers < n {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn skipped_notifications() {
+        for (mut tx, mut rx) in [channel(), sync_channel(4)] {
+            // Nobody waits: no notification
+            tx.send(0).unwrap();
+            tx.send(1).unwrap();
+            tx.send_all([2, 3]).unwrap();
+            while rx.try_recv().is_ok() {}
+            assert_eq!(notified(&rx), (0, 0));
+
+            // A blocked receiver is notified
+            let mut rx2 = rx.clone();
+            let t = thread::spawn(move || rx2.recv());
+            wait_for_receivers(&rx, 1);
+            tx.send(6).unwrap();
+            assert_eq!(t.join().unwrap(), Some(6));
+            assert_eq!(notified(&rx), (1, 0));
+        }
+
+        // A blocked sender is notified
+        let (mut tx, mut rx) = sync_channel(1);
+        tx.send(0).unwrap();
+        let t = thread::spawn(move || tx.send(1));
+        while rx.flavor.inner.lock().unwrap().waiting_senders == 0 {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(rx.recv(), Some(0));
+        t.join().unwrap().unwrap();
+        assert_eq!(rx.recv(), Some(1));
+        assert_eq!(notified(&rx), (0, 1));
+    }
+
+    #[test]
+    fn poisoned_lock() {
+        for (mut tx, mut rx) in [channel(), sync_channel(4)] {
+            tx.send(1).unwrap();
+            let tx2 = tx.clone();
+            // A sender thread panics while holding the lock
+            let res = thread::spawn(move || {
+                let _inner = tx2.flavor.inner.lock();
+                panic!("sender panicked");
+            })
+            .join();
+            assert!(res.is_err());
+            // The receiver keeps working
+            tx.send(2).unwrap();
+            assert_eq!(rx.recv(), Some(1));
+            assert_eq!(rx.recv(), Some(2));
+            drop(tx);
+            assert_eq!(rx.recv(), None);
+        }
+    }
+}
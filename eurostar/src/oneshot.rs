@@ -0,0 +1,127 @@
+use crate::sync::{self, Arc, Condvar, Mutex};
+use crate::{RecvError, SendError};
+
+// Oneshot channel: a single value is sent, so no queue nor senders counter is needed.
+struct Inner<T> {
+    value: Option<T>,
+    // The sender is gone, either consumed by send() or dropped
+    sender_gone: bool,
+    receiver_gone: bool,
+}
+
+struct Shared<T> {
+    inner: Mutex<Inner<T>>,
+    available: Condvar,
+}
+
+pub struct OneshotSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> OneshotSender<T> {
+    /// Fails if the receiver is gone, handing the value back.
+    pub fn send(self, t: T) -> Result<(), SendError<T>> {
+        // Acquire lock
+        let mut inner = sync::lock(&self.shared.inner);
+        if inner.receiver_gone {
+            return Err(SendError(t));
+        }
+        inner.value = Some(t);
+        // Release lock
+        drop(inner);
+        // The receiver is notified when self is dropped
+        Ok(())
+    }
+}
+
+impl<T> Drop for OneshotSender<T> {
+    fn drop(&mut self) {
+        // Acquire lock
+        let mut inner = sync::lock(&self.shared.inner);
+        inner.sender_gone = true;
+        // Release lock
+        drop(inner);
+        // Wake up the receiver, whether a value was sent or not
+        self.shared.available.notify_one();
+    }
+}
+
+pub struct OneshotReceiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> OneshotReceiver<T> {
+    pub fn recv(self) -> Result<T, RecvError> {
+        let mut inner = sync::lock(&self.shared.inner);
+        loop {
+            match inner.value.take() {
+                Some(t) => return Ok(t),
+                None if inner.sender_gone => return Err(RecvError),
+                None => inner = sync::wait(&self.shared.available, inner),
+            }
+        }
+    }
+}
+
+impl<T> Drop for OneshotReceiver<T> {
+    fn drop(&mut self) {
+        sync::lock(&self.shared.inner).receiver_gone = true;
+    }
+}
+
+pub fn oneshot<T>() -> (OneshotSender<T>, OneshotReceiver<T>) {
+    let inner = Inner {
+        value: None,
+        sender_gone: false,
+        receiver_gone: false,
+    };
+    let shared = Shared {
+        inner: Mutex::new(inner),
+        available: Condvar::new(),
+    };
+    let shared = Arc::new(shared);
+    let tx = OneshotSender {
+        shared: Arc::clone(&shared),
+    };
+    let rx = OneshotReceiver {
+        shared: Arc::clone(&shared),
+    };
+    (tx, rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn send_recv() {
+        let (tx, rx) = oneshot();
+        tx.send(42).unwrap();
+        assert_eq!(rx.recv(), Ok(42));
+    }
+
+    #[test]
+    fn send_from_thread() {
+        let (tx, rx) = oneshot();
+        let t = thread::spawn(move || tx.send(String::from("reply")));
+        assert_eq!(rx.recv(), Ok(String::from("reply")));
+        assert!(t.join().unwrap().is_ok());
+    }
+
+    #[test]
+    fn closed_tx() {
+        let (tx, rx) = oneshot::<()>();
+        // Drop the sender without sending
+        let t = thread::spawn(move || drop(tx));
+        assert_eq!(rx.recv(), Err(RecvError));
+        t.join().unwrap();
+    }
+
+    #[test]
+    fn closed_rx() {
+        let (tx, rx) = oneshot();
+        drop(rx);
+        assert_eq!(tx.send(12), Err(SendError(12)));
+    }
+}
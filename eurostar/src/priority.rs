@@ -0,0 +1,280 @@
+use crate::sync::{self, Arc, Condvar, Mutex, MutexGuard};
+use crate::{SendError, TryRecvError};
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+// Mutex + Condvar + BinaryHeap: unbounded channel where the greatest value is received first.
+// Same senders/receivers counting as the VecDeque flavor.
+struct Inner<T> {
+    heap: BinaryHeap<Entry<T>>,
+    senders: usize,
+    receivers: usize,
+    // Sequence number of the next value sent
+    next_seq: u64,
+    // A value's Ord panicked while the heap was being reordered: sending and receiving fail
+    poisoned: bool,
+}
+
+// Values of equal priority are received in the order they were sent
+struct Entry<T> {
+    value: T,
+    seq: Reverse<u64>,
+}
+
+impl<T: Ord> Ord for Entry<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.value
+            .cmp(&other.value)
+            .then_with(|| self.seq.cmp(&other.seq))
+    }
+}
+
+impl<T: Ord> PartialOrd for Entry<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord> PartialEq for Entry<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T: Ord> Eq for Entry<T> {}
+
+struct Shared<T> {
+    inner: Mutex<Inner<T>>,
+    available: Condvar,
+}
+
+// Unlike the other channels, a poisoned lock disconnects the channel when touching the heap:
+// a value's Ord panicking may leave it out of order.
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, Inner<T>> {
+        self.inner.lock().unwrap_or_else(|poisoned| {
+            let mut inner = poisoned.into_inner();
+            inner.poisoned = true;
+            inner
+        })
+    }
+
+    fn wait<'a>(&self, inner: MutexGuard<'a, Inner<T>>) -> MutexGuard<'a, Inner<T>> {
+        self.available.wait(inner).unwrap_or_else(|poisoned| {
+            let mut inner = poisoned.into_inner();
+            inner.poisoned = true;
+            inner
+        })
+    }
+}
+
+pub struct PrioritySender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: Ord> PrioritySender<T> {
+    /// Fails if all the receivers are gone, handing the value back.
+    pub fn send(&self, t: T) -> Result<(), SendError<T>> {
+        // Acquire lock
+        let mut inner = self.shared.lock();
+        if inner.receivers == 0 || inner.poisoned {
+            return Err(SendError(t));
+        }
+        let seq = Reverse(inner.next_seq);
+        inner.next_seq += 1;
+        inner.heap.push(Entry { value: t, seq });
+        // Release lock
+        drop(inner);
+        // Notify blocked thread
+        self.shared.available.notify_one();
+        Ok(())
+    }
+}
+
+impl<T> Clone for PrioritySender<T> {
+    fn clone(&self) -> Self {
+        sync::lock(&self.shared.inner).senders += 1;
+        PrioritySender {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for PrioritySender<T> {
+    fn drop(&mut self) {
+        let mut inner = sync::lock(&self.shared.inner);
+        inner.senders -= 1;
+        let was_last = inner.senders == 0;
+        drop(inner);
+        // If it was the last sender, notify the receiver threads
+        if was_last {
+            self.shared.available.notify_all();
+        }
+    }
+}
+
+pub struct PriorityReceiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: Ord> PriorityReceiver<T> {
+    /// Blocks until a value is sent, returns the greatest one waiting.
+    /// None once all the senders are gone and the channel is empty,
+    /// or once a value's `Ord` panicked in another thread.
+    pub fn recv(&mut self) -> Option<T> {
+        let mut inner = self.shared.lock();
+        loop {
+            if inner.poisoned {
+                return None;
+            }
+            match inner.heap.pop() {
+                Some(entry) => return Some(entry.value),
+                None if inner.senders == 0 => return None,
+                None => inner = self.shared.wait(inner),
+            }
+        }
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let mut inner = self.shared.lock();
+        if inner.poisoned {
+            return Err(TryRecvError::Disconnected);
+        }
+        match inner.heap.pop() {
+            Some(entry) => Ok(entry.value),
+            None if inner.senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+}
+
+impl<T> Clone for PriorityReceiver<T> {
+    fn clone(&self) -> Self {
+        sync::lock(&self.shared.inner).receivers += 1;
+        PriorityReceiver {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for PriorityReceiver<T> {
+    fn drop(&mut self) {
+        sync::lock(&self.shared.inner).receivers -= 1;
+    }
+}
+
+/// Unbounded channel receiving the greatest value first, and values of equal priority in order.
+/// Use `std::cmp::Reverse` to receive the smallest first, or an `Ord` impl comparing a priority key.
+pub fn priority_channel<T: Ord>() -> (PrioritySender<T>, PriorityReceiver<T>) {
+    let inner = Inner {
+        heap: BinaryHeap::new(),
+        senders: 1,
+        receivers: 1,
+        next_seq: 0,
+        poisoned: false,
+    };
+    let shared = Arc::new(Shared {
+        inner: Mutex::new(inner),
+        available: Condvar::new(),
+    });
+    let tx = PrioritySender {
+        shared: Arc::clone(&shared),
+    };
+    let rx = PriorityReceiver { shared };
+    (tx, rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Message {
+        priority: u8,
+        body: &'static str,
+    }
+
+    // Only the priority is compared
+    impl Ord for Message {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.priority.cmp(&other.priority)
+        }
+    }
+
+    impl PartialOrd for Message {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    #[test]
+    fn greatest_first() {
+        let (tx, mut rx) = priority_channel();
+        for i in [3, 1, 4, 1, 5] {
+            tx.send(i).unwrap();
+        }
+        drop(tx);
+        assert_eq!(
+            std::iter::from_fn(|| rx.recv()).collect::<Vec<_>>(),
+            [5, 4, 3, 1, 1]
+        );
+    }
+
+    #[test]
+    fn fifo_within_priority() {
+        let (tx, mut rx) = priority_channel();
+        let send = |priority, body| tx.send(Message { priority, body }).unwrap();
+        send(0, "bulk 1");
+        send(0, "bulk 2");
+        send(9, "control");
+        send(0, "bulk 3");
+        let bodies: Vec<_> = (0..4).map(|_| rx.recv().unwrap().body).collect();
+        assert_eq!(bodies, ["control", "bulk 1", "bulk 2", "bulk 3"]);
+    }
+
+    #[test]
+    fn closed_tx() {
+        let (tx, mut rx) = priority_channel::<()>();
+        let t = thread::spawn(move || drop(tx));
+        assert_eq!(rx.recv(), None);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+        t.join().unwrap();
+    }
+
+    #[test]
+    fn panicking_ord() {
+        #[derive(PartialEq, Eq)]
+        struct Flaky(i32);
+
+        impl Ord for Flaky {
+            fn cmp(&self, other: &Self) -> Ordering {
+                assert!(self.0 != 13 && other.0 != 13, "cmp panicked");
+                self.0.cmp(&other.0)
+            }
+        }
+
+        impl PartialOrd for Flaky {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        let (tx, mut rx) = priority_channel();
+        let tx2 = tx.clone();
+        tx.send(Flaky(1)).unwrap();
+        let res = thread::spawn(move || tx2.send(Flaky(13))).join();
+        assert!(res.is_err());
+        // The heap may be out of order: the channel is disconnected rather than silently wrong
+        assert_eq!(tx.send(Flaky(2)).map_err(|SendError(t)| t.0), Err(2));
+        assert!(matches!(rx.try_recv(), Err(TryRecvError::Disconnected)));
+        assert!(rx.recv().is_none());
+    }
+
+    #[test]
+    fn closed_rx() {
+        let (tx, rx) = priority_channel();
+        drop(rx);
+        assert_eq!(tx.send(12), Err(SendError(12)));
+    }
+}
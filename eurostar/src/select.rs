@@ -0,0 +1,300 @@
+use crate::sync::thread;
+use crate::waker::ThreadWaker;
+use crate::{ChannelFlavor, Receiver};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::Waker;
+
+// Channel state that can be watched by a Select.
+// Public but not exported, which seals ChannelFlavor.
+pub trait Selectable {
+    // A value is waiting or all the senders are gone: recv() would not block
+    fn is_ready(&self) -> bool;
+
+    fn watch(&self, waker: &Waker) -> usize;
+
+    fn unwatch(&self, token: usize);
+}
+
+impl<S: Selectable + ?Sized> Selectable for crate::sync::Arc<S> {
+    fn is_ready(&self) -> bool {
+        (**self).is_ready()
+    }
+
+    fn watch(&self, waker: &Waker) -> usize {
+        (**self).watch(waker)
+    }
+
+    fn unwatch(&self, token: usize) {
+        (**self).unwatch(token)
+    }
+}
+
+// Rotates the first receiver checked so that the first ones do not starve the others.
+// Always std's atomic: loom's cannot be used in a static, and this is only a heuristic.
+static START: AtomicUsize = AtomicUsize::new(0);
+
+/// Waits on several receivers at once.
+///
+/// The receivers are not borrowed, so `ready()` may spuriously report a receiver whose
+/// value was taken in the meantime, e.g. by a clone of the receiver: `try_recv()` then fails with `Empty`.
+#[derive(Default)]
+pub struct Select<'a> {
+    handles: Vec<Box<dyn Selectable + 'a>>,
+    // First receiver that had values in its own buffer when added
+    buffered: Option<usize>,
+}
+
+impl<'a> Select<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a receiver, returns its index.
+    pub fn recv<T: 'a, F: ChannelFlavor<T> + 'a>(&mut self, rx: &Receiver<T, F>) -> usize {
+        let index = self.handles.len();
+        if !rx.buffer.lock().is_empty() && self.buffered.is_none() {
+            self.buffered = Some(index);
+        }
+        self.handles.push(Box::new(rx.flavor.clone()));
+        index
+    }
+
+    /// Index of a receiver whose recv() would not block, if any.
+    pub fn try_ready(&mut self) -> Option<usize> {
+        if self.buffered.is_some() {
+            return self.buffered;
+        }
+        let len = self.handles.len();
+        let start = START.fetch_add(1, Ordering::Relaxed);
+        (0..len)
+            .map(|i| (start + i) % len)
+            .find(|&i| self.handles[i].is_ready())
+    }
+
+    /// Blocks until one of the receivers is ready, returns its index.
+    pub fn ready(&mut self) -> usize {
+        assert!(!self.handles.is_empty(), "no receiver to select on");
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        loop {
+            if let Some(index) = self.try_ready() {
+                return index;
+            }
+            let tokens: Vec<_> = self.handles.iter().map(|h| h.watch(&waker)).collect();
+            // Check again now that we are watching so that no wakeup is missed
+            let ready = self.try_ready();
+            if ready.is_none() {
+                thread::park();
+            }
+            for (handle, token) in self.handles.iter().zip(tokens) {
+                handle.unwatch(token);
+            }
+            if let Some(index) = ready {
+                return index;
+            }
+        }
+    }
+}
+
+/// Receives from the first of several receivers to be ready.
+///
+/// ```
+/// # use eurostar::{channel, select};
+/// let (mut tx1, mut rx1) = channel::<i32>();
+/// let (_tx2, mut rx2) = channel::<&str>();
+/// tx1.send(42).unwrap();
+/// select! {
+///     recv(rx1) -> msg => assert_eq!(msg, Ok(42)),
+///     recv(rx2) -> _msg => unreachable!(),
+/// }
+/// ```
+///
+/// Each arm gets a `Result<T, RecvError>`, the error meaning that all the senders are gone.
+/// With a `default` arm, `select!` does not block if no receiver is ready.
+/// The receiver expressions are evaluated several times.
+#[macro_export]
+macro_rules! select {
+    ($(recv($rx:expr) -> $res:pat => $body:expr),+ , default => $default:expr $(,)?) => {
+        $crate::select!(@slots [] [$(($rx, $res, $body))+] (default $default))
+    };
+    ($(recv($rx:expr) -> $res:pat => $body:expr),+ $(,)?) => {
+        $crate::select!(@slots [] [$(($rx, $res, $body))+] (block))
+    };
+    // One slot per receiver for its result, as their types differ
+    (@slots [$($done:tt)*] [($rx:expr, $res:pat, $body:expr) $($rest:tt)*] $mode:tt) => {{
+        let mut slot = None;
+        $crate::select!(@slots [$($done)* (slot, $rx, $res, $body)] [$($rest)*] $mode)
+    }};
+    // The bodies run after the retry loop, so that `break` and `continue` in an arm apply to the caller's loops
+    (@slots [$(($slot:ident, $rx:expr, $res:pat, $body:expr))+] [] (block)) => {{
+        loop {
+            let index = {
+                let mut sel = $crate::Select::new();
+                $( sel.recv(&$rx); )+
+                sel.ready()
+            };
+            $crate::select!(@try index, 0usize, (), $(($slot, $rx))+);
+        }
+        $( if let Some(res) = $slot {
+            let $res = res;
+            $body
+        } else )+ {
+            unreachable!()
+        }
+    }};
+    (@slots [$(($slot:ident, $rx:expr, $res:pat, $body:expr))+] [] (default $default:expr)) => {{
+        let selected = loop {
+            let index = {
+                let mut sel = $crate::Select::new();
+                $( sel.recv(&$rx); )+
+                sel.try_ready()
+            };
+            let index = match index {
+                Some(index) => index,
+                None => break false,
+            };
+            $crate::select!(@try index, 0usize, true, $(($slot, $rx))+);
+        };
+        $( if let Some(res) = $slot {
+            let $res = res;
+            $body
+        } else )+ if selected {
+            unreachable!()
+        } else {
+            $default
+        }
+    }};
+    // Receives from the selected receiver into its slot and leaves the retry loop with `$done`
+    (@try $index:ident, $n:expr, $done:expr, ($slot:ident, $rx:expr) $($rest:tt)*) => {
+        if $index == $n {
+            match $rx.try_recv() {
+                // Spurious: the value was taken by another receiver, select again
+                Err($crate::TryRecvError::Empty) => continue,
+                res => {
+                    $slot = Some(res.map_err(|_| $crate::RecvError));
+                    break $done;
+                }
+            }
+        }
+        $crate::select!(@try $index, $n + 1, $done, $($rest)*);
+    };
+    (@try $index:ident, $n:expr, $done:expr,) => {};
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{channel, list_channel, sync_channel, RecvError, Select};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn ready() {
+        let (mut tx1, rx1) = channel::<i32>();
+        let (_tx2, rx2) = list_channel::<i32>();
+        tx1.send(1).unwrap();
+        let mut sel = Select::new();
+        assert_eq!(sel.recv(&rx1), 0);
+        assert_eq!(sel.recv(&rx2), 1);
+        assert_eq!(sel.ready(), 0);
+    }
+
+    #[test]
+    fn try_ready() {
+        let (mut tx1, rx1) = channel::<i32>();
+        let (tx2, rx2) = sync_channel::<i32>(1);
+        let mut sel = Select::new();
+        sel.recv(&rx1);
+        sel.recv(&rx2);
+        assert_eq!(sel.try_ready(), None);
+        // A disconnected receiver is ready
+        drop(tx2);
+        assert_eq!(sel.try_ready(), Some(1));
+        drop(rx2);
+        let mut sel = Select::new();
+        sel.recv(&rx1);
+        tx1.send(1).unwrap();
+        assert_eq!(sel.try_ready(), Some(0));
+    }
+
+    #[test]
+    fn wakes_up() {
+        let (_tx1, rx1) = channel::<i32>();
+        let (mut tx2, rx2) = list_channel::<i32>();
+        let t = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            tx2.send(2).unwrap();
+        });
+        let mut sel = Select::new();
+        sel.recv(&rx1);
+        sel.recv(&rx2);
+        assert_eq!(sel.ready(), 1);
+        t.join().unwrap();
+    }
+
+    #[test]
+    fn select_macro() {
+        let (mut tx1, mut rx1) = channel::<i32>();
+        let (mut tx2, mut rx2) = sync_channel::<String>(1);
+        let t = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            tx2.send(String::from("two")).unwrap();
+            thread::sleep(Duration::from_millis(20));
+            tx1.send(1).unwrap();
+        });
+        let first = crate::select! {
+            recv(rx1) -> msg => format!("rx1: {:?}", msg),
+            recv(rx2) -> msg => format!("rx2: {:?}", msg),
+        };
+        assert_eq!(first, r#"rx2: Ok("two")"#);
+        let second = crate::select! {
+            recv(rx1) -> msg => msg.map(|i| i.to_string()),
+            recv(rx2) -> msg => msg,
+        };
+        assert_eq!(second, Ok(String::from("1")));
+        t.join().unwrap();
+        // Both senders are gone
+        let disconnected = crate::select! {
+            recv(rx1) -> msg => msg.map(|_| ()),
+            recv(rx2) -> msg => msg.map(|_| ()),
+        };
+        assert_eq!(disconnected, Err(RecvError));
+    }
+
+    #[test]
+    fn select_macro_default() {
+        let (_tx, mut rx) = channel::<i32>();
+        let res = crate::select! {
+            recv(rx) -> msg => msg.ok(),
+            default => None,
+        };
+        assert_eq!(res, None);
+    }
+
+    #[test]
+    fn select_macro_break() {
+        let (mut tx1, mut rx1) = channel::<i32>();
+        let (tx2, mut rx2) = channel::<i32>();
+        tx1.send_all(0..3).unwrap();
+        drop(tx1);
+        drop(tx2);
+        let mut received = Vec::new();
+        let mut rounds = 0;
+        // `break` and `continue` in an arm apply to this loop
+        loop {
+            rounds += 1;
+            assert!(rounds < 10, "break did not leave the loop");
+            crate::select! {
+                recv(rx1) -> msg => match msg {
+                    Ok(i) => {
+                        received.push(i);
+                        continue;
+                    }
+                    Err(_) => break,
+                },
+                recv(rx2) -> _msg => break,
+            }
+        }
+        assert!(received.len() <= 3);
+        assert_eq!(received, (0..received.len() as i32).collect::<Vec<_>>());
+    }
+}
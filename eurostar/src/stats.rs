@@ -0,0 +1,116 @@
+#[cfg(feature = "stats")]
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+#[cfg(feature = "stats")]
+use std::time::{Duration, Instant};
+
+/// Cumulative counters of a channel, shared by all its senders and receivers.
+#[cfg(feature = "stats")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub sent: u64,
+    pub received: u64,
+    /// Greatest number of values waiting in the channel's queue at once, sampled on every send.
+    /// An upper bound on the lock-free flavors, which may count values still being sent or received.
+    pub max_len: usize,
+    /// Time spent by the senders and receivers blocked on the channel.
+    pub blocked: Duration,
+}
+
+// Counters kept in the channel's shared state.
+// Without the `stats` feature they are empty and updating them is a no-op.
+#[derive(Default)]
+pub struct Counters {
+    #[cfg(feature = "stats")]
+    sent: AtomicU64,
+    #[cfg(feature = "stats")]
+    received: AtomicU64,
+    #[cfg(feature = "stats")]
+    max_len: AtomicUsize,
+    // Values in the queue of a flavor that cannot tell its length when sending
+    #[cfg(feature = "stats")]
+    queued: AtomicUsize,
+    #[cfg(feature = "stats")]
+    blocked_nanos: AtomicU64,
+}
+
+impl Counters {
+    #[cfg_attr(not(feature = "stats"), allow(unused_variables))]
+    pub(crate) fn on_send(&self, n: usize) {
+        #[cfg(feature = "stats")]
+        self.sent.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    // Called by the flavors with the length of their queue, right after a send
+    #[cfg_attr(not(feature = "stats"), allow(unused_variables))]
+    pub(crate) fn on_len(&self, len: usize) {
+        #[cfg(feature = "stats")]
+        self.max_len.fetch_max(len, Ordering::Relaxed);
+    }
+
+    // Called by the flavors that do not know the length of their queue, before pushing `n` values.
+    // Counted before they are in the queue so that on_pop() never gets ahead.
+    #[cfg_attr(not(feature = "stats"), allow(unused_variables))]
+    pub(crate) fn on_push(&self, n: usize) {
+        #[cfg(feature = "stats")]
+        {
+            let len = self.queued.fetch_add(n, Ordering::Relaxed) + n;
+            self.on_len(len);
+        }
+    }
+
+    // Called by the flavors calling on_push(), after popping `n` values
+    #[cfg_attr(not(feature = "stats"), allow(unused_variables))]
+    pub(crate) fn on_pop(&self, n: usize) {
+        #[cfg(feature = "stats")]
+        self.queued.fetch_sub(n, Ordering::Relaxed);
+    }
+
+    #[cfg_attr(not(feature = "stats"), allow(unused_variables))]
+    pub(crate) fn on_recv(&self, n: usize) {
+        #[cfg(feature = "stats")]
+        self.received.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    // Adds the time until the returned guard is dropped to the blocked time
+    pub(crate) fn blocking(&self) -> Blocking<'_> {
+        Blocking {
+            #[cfg(feature = "stats")]
+            counters: self,
+            #[cfg(feature = "stats")]
+            start: Instant::now(),
+            #[cfg(not(feature = "stats"))]
+            counters: std::marker::PhantomData,
+        }
+    }
+
+    #[cfg(feature = "stats")]
+    pub(crate) fn stats(&self) -> Stats {
+        Stats {
+            sent: self.sent.load(Ordering::Relaxed),
+            received: self.received.load(Ordering::Relaxed),
+            max_len: self.max_len.load(Ordering::Relaxed),
+            blocked: Duration::from_nanos(self.blocked_nanos.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+pub(crate) struct Blocking<'a> {
+    #[cfg(feature = "stats")]
+    counters: &'a Counters,
+    #[cfg(feature = "stats")]
+    start: Instant,
+    #[cfg(not(feature = "stats"))]
+    counters: std::marker::PhantomData<&'a Counters>,
+}
+
+impl Drop for Blocking<'_> {
+    fn drop(&mut self) {
+        #[cfg(feature = "stats")]
+        {
+            let nanos = self.start.elapsed().as_nanos() as u64;
+            self.counters
+                .blocked_nanos
+                .fetch_add(nanos, Ordering::Relaxed);
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::future::AsyncSend;
+use crate::{ChannelFlavor, Receiver, SendError, Sender};
+use futures_core::Stream;
+use futures_sink::Sink;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+// futures::Stream and futures::Sink implementations, behind the `futures` feature.
+
+impl<T, F: ChannelFlavor<T>> Stream for Receiver<T, F> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.get_mut().poll_recv(cx)
+    }
+}
+
+impl<T, F: ChannelFlavor<T>> Sender<T, F> {
+    // Sends the value given to start_send(), if any
+    fn poll_sink(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), SendError<T>>> {
+        let send = match &mut self.sink {
+            Some(send) => send,
+            None => return Poll::Ready(Ok(())),
+        };
+        let res = futures_core::ready!(self.flavor.poll_send(cx, &mut send.value, &mut send.state));
+        self.sink = None;
+        Poll::Ready(res.inspect(|_| self.flavor.counters().on_send(1)))
+    }
+}
+
+impl<T, F: ChannelFlavor<T>> Sink<T> for Sender<T, F> {
+    type Error = SendError<T>;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_sink(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, t: T) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        // Might have to wait for room, the value is sent by the next poll
+        assert!(this.sink.is_none(), "start_send called before poll_ready");
+        this.sink = Some(AsyncSend::new(t));
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_sink(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // The channel is closed when the last Sender is dropped
+        self.get_mut().poll_sink(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::waker::block_on;
+    use crate::{channel, list_channel, sync_channel, ChannelFlavor, Receiver, SendError, Sender};
+    use futures_core::Stream;
+    use futures_sink::Sink;
+    use std::future::poll_fn;
+    use std::pin::Pin;
+    use std::thread;
+    use std::time::Duration;
+
+    fn next<T, F: ChannelFlavor<T>>(rx: &mut Receiver<T, F>) -> Option<T> {
+        block_on(poll_fn(|cx| Pin::new(&mut *rx).poll_next(cx)))
+    }
+
+    fn send<T, F: ChannelFlavor<T>>(tx: &mut Sender<T, F>, t: T) -> Result<(), SendError<T>> {
+        block_on(poll_fn(|cx| Pin::new(&mut *tx).poll_ready(cx)))?;
+        Pin::new(&mut *tx).start_send(t)?;
+        block_on(poll_fn(|cx| Pin::new(&mut *tx).poll_flush(cx)))
+    }
+
+    #[test]
+    fn stream() {
+        let (mut tx, mut rx) = channel();
+        let t = thread::spawn(move || {
+            for i in 0..10 {
+                thread::sleep(Duration::from_millis(1));
+                tx.send(i).unwrap();
+            }
+        });
+        let received: Vec<_> = std::iter::from_fn(|| next(&mut rx)).collect();
+        assert_eq!(received, (0..10).collect::<Vec<_>>());
+        t.join().unwrap();
+    }
+
+    #[test]
+    fn sink() {
+        fn check<F>((mut tx, mut rx): (Sender<i32, F>, Receiver<i32, F>))
+        where
+            F: ChannelFlavor<i32> + Send + Sync + 'static,
+        {
+            let t = thread::spawn(move || {
+                for i in 0..10 {
+                    send(&mut tx, i).unwrap();
+                }
+            });
+            assert_eq!(rx.iter().collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+            t.join().unwrap();
+        }
+        check(channel());
+        check(sync_channel(1));
+        check(sync_channel(0));
+        check(list_channel());
+    }
+
+    #[test]
+    fn sink_closed() {
+        let (mut tx, rx) = sync_channel(1);
+        send(&mut tx, 1).unwrap();
+        drop(rx);
+        assert_eq!(send(&mut tx, 2), Err(SendError(2)));
+    }
+}
@@ -0,0 +1,53 @@
+use std::sync::PoisonError;
+use std::time::Duration;
+
+// Synchronization primitives used by the channels, swapped for loom's when built with `--cfg loom`
+// so that the model-checked tests explore all the interleavings.
+#[cfg(loom)]
+pub(crate) use loom::{
+    sync::{atomic, Arc, Condvar, Mutex, MutexGuard},
+    thread,
+};
+#[cfg(not(loom))]
+pub(crate) use std::{
+    sync::{atomic, Arc, Condvar, Mutex, MutexGuard},
+    thread,
+};
+
+// Locking that recovers from poisoning.
+// The values are only moved in and out of the state behind the channels' locks, which no panic leaves
+// half-updated, so a thread panicking while holding a lock must not take down all the other threads
+// using the channel. The user code run under a lock is accounted for: the broadcast channel clones a value
+// before moving on to the next one, the broadcast and watch channels drop the values they overwrite
+// once the lock is released, and the priority channel, whose heap a value's Ord may leave
+// out of order, gets disconnected instead.
+
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+pub(crate) fn wait<'a, T>(condvar: &Condvar, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+    condvar.wait(guard).unwrap_or_else(PoisonError::into_inner)
+}
+
+pub(crate) fn wait_timeout<'a, T>(
+    condvar: &Condvar,
+    guard: MutexGuard<'a, T>,
+    timeout: Duration,
+) -> MutexGuard<'a, T> {
+    match condvar.wait_timeout(guard, timeout) {
+        Ok((guard, _)) => guard,
+        Err(poisoned) => poisoned.into_inner().0,
+    }
+}
+
+// loom does not model timeouts: returning right away is a spurious wakeup, which callers handle
+#[cfg(loom)]
+pub(crate) fn park_timeout(_timeout: Duration) {
+    thread::yield_now();
+}
+
+#[cfg(not(loom))]
+pub(crate) fn park_timeout(timeout: Duration) {
+    thread::park_timeout(timeout);
+}
@@ -0,0 +1,233 @@
+use crate::{sync_channel, ChannelFlavor, Receiver, Sender, TrySendError};
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+// Always std's: the timer is shared by all the channels through a static, loom's cannot be used there
+use std::sync::{Condvar, Mutex, MutexGuard, OnceLock, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Timer channels: a single thread sleeps until the earliest deadline and sends the current Instant
+// to the channel it belongs to. The channels are regular bounded receivers, they can be selected on.
+struct Entry {
+    deadline: Instant,
+    // Some for tick(): rescheduled after every send
+    period: Option<Duration>,
+    tx: Sender<Instant>,
+}
+
+// Earliest deadline first in the heap
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        Reverse(self.deadline).cmp(&Reverse(other.deadline))
+    }
+}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Entry {}
+
+struct Timer {
+    entries: Mutex<BinaryHeap<Entry>>,
+    // Signaled when a timer is added, which may be due before the one the thread is sleeping on
+    added: Condvar,
+}
+
+impl Timer {
+    // Shared timer, its thread is started by the first call
+    fn get() -> &'static Timer {
+        static TIMER: OnceLock<Timer> = OnceLock::new();
+        let mut started = false;
+        let timer = TIMER.get_or_init(|| {
+            started = true;
+            Timer {
+                entries: Mutex::new(BinaryHeap::new()),
+                added: Condvar::new(),
+            }
+        });
+        if started {
+            thread::Builder::new()
+                .name("eurostar-timer".into())
+                .spawn(move || timer.run())
+                .expect("failed to spawn the timer thread");
+        }
+        timer
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BinaryHeap<Entry>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // A deadline too far to be represented never fires
+    fn add(&self, deadline: Option<Instant>, period: Option<Duration>) -> Receiver<Instant> {
+        // A single slot: ticks are dropped rather than queued while the receiver is behind
+        let (tx, rx) = sync_channel(1);
+        let deadline = match deadline {
+            Some(deadline) => deadline,
+            None => {
+                never(tx);
+                return rx;
+            }
+        };
+        self.lock().push(Entry {
+            deadline,
+            period,
+            tx,
+        });
+        self.added.notify_one();
+        rx
+    }
+
+    fn run(&self) {
+        let mut due = Vec::new();
+        let mut rescheduled = Vec::new();
+        loop {
+            let mut entries = self.lock();
+            entries.extend(rescheduled.drain(..));
+            let now = loop {
+                let now = Instant::now();
+                match entries.peek().map(|entry| entry.deadline) {
+                    Some(deadline) if deadline <= now => break now,
+                    Some(deadline) => {
+                        entries = match self.added.wait_timeout(entries, deadline - now) {
+                            Ok((entries, _)) => entries,
+                            Err(poisoned) => poisoned.into_inner().0,
+                        };
+                    }
+                    None => {
+                        entries = self
+                            .added
+                            .wait(entries)
+                            .unwrap_or_else(PoisonError::into_inner);
+                    }
+                }
+            };
+            while entries.peek().is_some_and(|entry| entry.deadline <= now) {
+                due.push(entries.pop().expect("due timer"));
+            }
+            // Sending and dropping the senders wakes the receivers, which may run a Waker inline:
+            // not under the lock
+            drop(entries);
+            for mut entry in due.drain(..) {
+                match (entry.tx.try_send(now), entry.period) {
+                    // The receiver is gone
+                    (Err(TrySendError::Disconnected(_)), _) => {}
+                    (_, Some(period)) => {
+                        // Fell behind: skip the missed ticks
+                        let deadline = entry
+                            .deadline
+                            .checked_add(period)
+                            .filter(|&deadline| deadline > now)
+                            .or_else(|| now.checked_add(period));
+                        match deadline {
+                            Some(deadline) => {
+                                entry.deadline = deadline;
+                                rescheduled.push(entry);
+                            }
+                            None => never(entry.tx),
+                        }
+                    }
+                    // after(): the sender is dropped once the value is sent
+                    (_, None) => {}
+                }
+            }
+        }
+    }
+}
+
+// Drops the sender of a timer that never fires, without disconnecting its receiver:
+// the channel counts a sender that nothing holds, so no entry is kept around until the receiver is dropped
+fn never(tx: Sender<Instant>) {
+    tx.flavor.add_sender();
+}
+
+/// Receiver getting the current `Instant` every `period`, from a timer thread shared by all
+/// the timer channels. Ticks are dropped while the previous one was not received yet.
+/// Panics if `period` is zero.
+pub fn tick(period: Duration) -> Receiver<Instant> {
+    assert!(!period.is_zero(), "tick period must not be zero");
+    Timer::get().add(Instant::now().checked_add(period), Some(period))
+}
+
+/// Receiver getting the current `Instant` once, after `delay`. The channel is disconnected afterwards.
+pub fn after(delay: Duration) -> Receiver<Instant> {
+    Timer::get().add(Instant::now().checked_add(delay), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{channel, select, RecvTimeoutError, Select};
+
+    #[test]
+    fn after() {
+        let start = Instant::now();
+        let mut rx = super::after(Duration::from_millis(20));
+        let fired = rx.recv().unwrap();
+        assert!(fired >= start + Duration::from_millis(20));
+        // Only fires once
+        assert_eq!(rx.recv(), None);
+    }
+
+    #[test]
+    fn tick() {
+        let start = Instant::now();
+        let mut rx = super::tick(Duration::from_millis(10));
+        let ticks: Vec<_> = rx.iter().take(3).collect();
+        assert!(ticks[0] >= start + Duration::from_millis(10));
+        assert!(ticks.windows(2).all(|w| w[1] > w[0]));
+        assert!(ticks[2] >= start + Duration::from_millis(30));
+    }
+
+    #[test]
+    fn never() {
+        // Too far to be represented: never fires, and is not disconnected either
+        let mut after = super::after(Duration::MAX);
+        let mut tick = super::tick(Duration::MAX);
+        let mut soon = super::after(Duration::from_millis(10));
+        let timeout = Duration::from_millis(30);
+        assert_eq!(after.recv_timeout(timeout), Err(RecvTimeoutError::Timeout));
+        assert_eq!(tick.recv_timeout(timeout), Err(RecvTimeoutError::Timeout));
+        // Not registered in the timer: the sender is only counted
+        assert_eq!(after.sender_count(), 1);
+        assert!(Timer::get()
+            .lock()
+            .iter()
+            .all(|entry| !crate::sync::Arc::ptr_eq(&entry.tx.flavor, &after.flavor)));
+        // Other timers still fire
+        assert!(soon.try_recv().is_ok());
+    }
+
+    #[test]
+    fn earliest_first() {
+        // Added while the thread sleeps on the later one
+        let mut late = super::after(Duration::from_millis(200));
+        let mut early = super::after(Duration::from_millis(10));
+        let mut sel = Select::new();
+        sel.recv(&late);
+        sel.recv(&early);
+        assert_eq!(sel.ready(), 1);
+        assert!(early.try_recv().is_ok());
+        assert!(late.recv().is_some());
+    }
+
+    #[test]
+    fn select_timeout() {
+        let (_tx, mut rx) = channel::<i32>();
+        let mut timeout = super::after(Duration::from_millis(10));
+        let timed_out = select! {
+            recv(rx) -> _msg => false,
+            recv(timeout) -> _fired => true,
+        };
+        assert!(timed_out);
+    }
+}
@@ -0,0 +1,91 @@
+use crate::sync::atomic::{self, AtomicBool, Ordering};
+use crate::sync::thread::Thread;
+use crate::sync::{self, Mutex};
+use std::sync::Arc;
+use std::task::{Wake, Waker};
+
+// Waker unparking a blocked thread. Wakers are always built from std's Arc.
+pub(crate) struct ThreadWaker(pub(crate) Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark()
+    }
+}
+
+// Wakers of the operations watching a channel (Select, futures), all woken up on every change.
+pub(crate) struct Wakers {
+    inner: Mutex<Entries>,
+    // Checked by senders without taking the lock
+    empty: AtomicBool,
+}
+
+struct Entries {
+    wakers: Vec<(usize, Waker)>,
+    next_token: usize,
+}
+
+impl Wakers {
+    pub(crate) fn new() -> Self {
+        Wakers {
+            inner: Mutex::new(Entries {
+                wakers: Vec::new(),
+                next_token: 0,
+            }),
+            empty: AtomicBool::new(true),
+        }
+    }
+
+    // Returns a token to unregister the waker. The channel must be checked again after registering
+    // as a change happening right before would not wake it up.
+    pub(crate) fn register(&self, waker: &Waker) -> usize {
+        let mut inner = sync::lock(&self.inner);
+        let token = inner.next_token;
+        inner.next_token += 1;
+        inner.wakers.push((token, waker.clone()));
+        self.empty.store(false, Ordering::SeqCst);
+        drop(inner);
+        // Pairs with the fence in notify()
+        atomic::fence(Ordering::SeqCst);
+        token
+    }
+
+    pub(crate) fn unregister(&self, token: usize) {
+        let mut inner = sync::lock(&self.inner);
+        inner.wakers.retain(|(t, _)| *t != token);
+        self.empty.store(inner.wakers.is_empty(), Ordering::SeqCst);
+    }
+
+    // Wakes up and unregisters all the wakers
+    pub(crate) fn notify(&self) {
+        atomic::fence(Ordering::SeqCst);
+        // Fast path: nobody is watching, no lock is taken
+        if self.empty.load(Ordering::SeqCst) {
+            return;
+        }
+        let mut inner = sync::lock(&self.inner);
+        let wakers = std::mem::take(&mut inner.wakers);
+        self.empty.store(true, Ordering::SeqCst);
+        drop(inner);
+        for (_, waker) in wakers {
+            waker.wake();
+        }
+    }
+}
+
+// Minimal executor for the tests: polls the future on the current thread, parking in between
+#[cfg(test)]
+pub(crate) fn block_on<F: std::future::Future>(fut: F) -> F::Output {
+    use crate::sync::thread;
+    use std::task::{Context, Poll};
+
+    let mut fut = Box::pin(fut);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match fut.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
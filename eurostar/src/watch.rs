@@ -0,0 +1,222 @@
+use crate::sync::{self, Arc, Condvar, Mutex, MutexGuard};
+use crate::{RecvError, SendError};
+use std::ops::Deref;
+
+// Watch channel: a single slot overwritten by each send, no queue.
+// Receivers compare the slot's version with the last one they saw to know if the value changed.
+struct Inner<T> {
+    value: T,
+    // Incremented on every send
+    version: u64,
+    sender_gone: bool,
+    receivers: usize,
+}
+
+struct Shared<T> {
+    inner: Mutex<Inner<T>>,
+    changed: Condvar,
+}
+
+pub struct WatchSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> WatchSender<T> {
+    /// Replaces the current value. Fails if there is no receiver left, handing the value back.
+    pub fn send(&self, t: T) -> Result<(), SendError<T>> {
+        // Acquire lock
+        let mut inner = sync::lock(&self.shared.inner);
+        if inner.receivers == 0 {
+            return Err(SendError(t));
+        }
+        let old = std::mem::replace(&mut inner.value, t);
+        inner.version += 1;
+        // Release lock
+        drop(inner);
+        self.shared.changed.notify_all();
+        // Dropped last: its Drop may panic
+        drop(old);
+        Ok(())
+    }
+
+    /// New receiver, considering the current value as seen.
+    pub fn subscribe(&self) -> WatchReceiver<T> {
+        let mut inner = sync::lock(&self.shared.inner);
+        inner.receivers += 1;
+        WatchReceiver {
+            shared: Arc::clone(&self.shared),
+            seen: inner.version,
+        }
+    }
+}
+
+impl<T> Drop for WatchSender<T> {
+    fn drop(&mut self) {
+        sync::lock(&self.shared.inner).sender_gone = true;
+        // Wake up the receivers waiting in changed()
+        self.shared.changed.notify_all();
+    }
+}
+
+pub struct WatchReceiver<T> {
+    shared: Arc<Shared<T>>,
+    // Last version returned by changed()
+    seen: u64,
+}
+
+impl<T> WatchReceiver<T> {
+    /// Current value. The channel is locked until the returned guard is dropped.
+    pub fn borrow(&self) -> WatchRef<'_, T> {
+        WatchRef(sync::lock(&self.shared.inner))
+    }
+
+    /// Version of the current value, incremented on every send.
+    pub fn version(&self) -> u64 {
+        sync::lock(&self.shared.inner).version
+    }
+
+    /// Blocks until a value newer than the last one seen is sent, marking it as seen.
+    /// Fails once the sender is gone and the current value was already seen.
+    pub fn changed(&mut self) -> Result<(), RecvError> {
+        let mut inner = sync::lock(&self.shared.inner);
+        loop {
+            if inner.version != self.seen {
+                self.seen = inner.version;
+                return Ok(());
+            }
+            if inner.sender_gone {
+                return Err(RecvError);
+            }
+            inner = sync::wait(&self.shared.changed, inner);
+        }
+    }
+}
+
+// The clone has seen the same version
+impl<T> Clone for WatchReceiver<T> {
+    fn clone(&self) -> Self {
+        sync::lock(&self.shared.inner).receivers += 1;
+        WatchReceiver {
+            shared: Arc::clone(&self.shared),
+            seen: self.seen,
+        }
+    }
+}
+
+impl<T> Drop for WatchReceiver<T> {
+    fn drop(&mut self) {
+        sync::lock(&self.shared.inner).receivers -= 1;
+    }
+}
+
+/// Value borrowed from a watch channel.
+pub struct WatchRef<'a, T>(MutexGuard<'a, Inner<T>>);
+
+impl<T> Deref for WatchRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0.value
+    }
+}
+
+/// Channel only holding the latest value sent, starting with `initial`.
+pub fn watch<T>(initial: T) -> (WatchSender<T>, WatchReceiver<T>) {
+    let inner = Inner {
+        value: initial,
+        version: 0,
+        sender_gone: false,
+        receivers: 1,
+    };
+    let shared = Arc::new(Shared {
+        inner: Mutex::new(inner),
+        changed: Condvar::new(),
+    });
+    let tx = WatchSender {
+        shared: Arc::clone(&shared),
+    };
+    let rx = WatchReceiver { shared, seen: 0 };
+    (tx, rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn borrow_latest() {
+        let (tx, rx) = watch("starting");
+        assert_eq!(*rx.borrow(), "starting");
+        tx.send("running").unwrap();
+        tx.send("stopping").unwrap();
+        // Only the latest value is kept
+        assert_eq!(*rx.borrow(), "stopping");
+        assert_eq!(rx.version(), 2);
+    }
+
+    #[test]
+    fn changed() {
+        let (tx, mut rx) = watch(0);
+        let t = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            tx.send(1).unwrap();
+            thread::sleep(Duration::from_millis(20));
+        });
+        assert_eq!(rx.changed(), Ok(()));
+        assert_eq!(*rx.borrow(), 1);
+        // The sender is dropped without sending anything newer
+        assert_eq!(rx.changed(), Err(RecvError));
+        t.join().unwrap();
+    }
+
+    #[test]
+    fn changed_after_sender_gone() {
+        let (tx, mut rx) = watch(0);
+        tx.send(1).unwrap();
+        drop(tx);
+        // The last value was not seen yet
+        assert_eq!(rx.changed(), Ok(()));
+        assert_eq!(rx.changed(), Err(RecvError));
+    }
+
+    #[test]
+    fn subscribe() {
+        let (tx, _rx) = watch(0);
+        tx.send(1).unwrap();
+        let mut rx = tx.subscribe();
+        let mut rx2 = rx.clone();
+        tx.send(2).unwrap();
+        assert_eq!(rx.changed(), Ok(()));
+        assert_eq!(rx2.changed(), Ok(()));
+        assert_eq!(*rx2.borrow(), 2);
+    }
+
+    #[test]
+    fn panicking_drop() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        struct D(i32, bool);
+
+        impl Drop for D {
+            fn drop(&mut self) {
+                assert!(!self.1 || thread::panicking(), "drop panicked");
+            }
+        }
+
+        let (tx, mut rx) = watch(D(0, true));
+        // Replaces the first value, which panics once the second one was sent
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| tx.send(D(1, false)))).is_err());
+        assert_eq!(rx.changed(), Ok(()));
+        assert_eq!((*rx.borrow()).0, 1);
+        assert_eq!(rx.version(), 1);
+    }
+
+    #[test]
+    fn closed_rx() {
+        let (tx, rx) = watch(0);
+        drop(rx);
+        assert_eq!(tx.send(12), Err(SendError(12)));
+    }
+}
@@ -0,0 +1,139 @@
+// Model-checked tests: loom runs each model under all the possible interleavings of its threads.
+// Run with: RUSTFLAGS="--cfg loom" cargo test --release --test loom
+#![cfg(loom)]
+
+use eurostar::{
+    block_channel, channel, list_channel, oneshot, sync_channel, ChannelFlavor, Receiver, Sender,
+};
+use loom::thread;
+
+// Constructor of a channel, called inside the model
+type Channel<F> = fn() -> (Sender<usize, F>, Receiver<usize, F>);
+
+trait Flavor: ChannelFlavor<usize> + Send + Sync + 'static {}
+impl<F: ChannelFlavor<usize> + Send + Sync + 'static> Flavor for F {}
+
+#[test]
+fn concurrent_send() {
+    fn model<F: Flavor>(flavor: Channel<F>) {
+        loom::model(move || {
+            let (tx1, mut rx) = flavor();
+            let mut tx2 = tx1.clone();
+            let t = thread::spawn(move || {
+                let mut tx1 = tx1;
+                tx1.send(1).unwrap();
+            });
+            tx2.send(2).unwrap();
+            drop(tx2);
+            let mut received: Vec<_> = rx.iter().collect();
+            received.sort_unstable();
+            assert_eq!(received, [1, 2]);
+            t.join().unwrap();
+        });
+    }
+    model(channel);
+    model(list_channel);
+    model(block_channel);
+}
+
+#[test]
+fn last_sender_drop_wakes_receiver() {
+    fn model<F: Flavor>(flavor: Channel<F>) {
+        loom::model(move || {
+            let (tx1, mut rx) = flavor();
+            let tx2 = tx1.clone();
+            let t1 = thread::spawn(move || drop(tx1));
+            let t2 = thread::spawn(move || drop(tx2));
+            // Must not park forever, whichever sender goes last
+            assert_eq!(rx.recv(), None);
+            t1.join().unwrap();
+            t2.join().unwrap();
+        });
+    }
+    model(channel);
+    model(list_channel);
+    model(block_channel);
+}
+
+#[test]
+fn send_then_drop() {
+    fn model<F: Flavor>(flavor: Channel<F>) {
+        loom::model(move || {
+            let (mut tx, mut rx) = flavor();
+            let t = thread::spawn(move || {
+                tx.send(42).unwrap();
+            });
+            // The value sent right before the last sender left is not lost
+            assert_eq!(rx.recv(), Some(42));
+            assert_eq!(rx.recv(), None);
+            t.join().unwrap();
+        });
+    }
+    model(channel);
+    model(list_channel);
+    model(block_channel);
+}
+
+#[test]
+fn receiver_drop() {
+    fn model<F: Flavor>(flavor: Channel<F>) {
+        loom::model(move || {
+            let (mut tx, rx) = flavor();
+            let t = thread::spawn(move || drop(rx));
+            // Either sent before the receiver left, or handed back
+            let _ = tx.send(1);
+            t.join().unwrap();
+            assert!(tx.send(2).is_err());
+        });
+    }
+    model(channel);
+    model(list_channel);
+    model(block_channel);
+}
+
+#[test]
+fn close_wakes_receiver() {
+    fn model<F: Flavor>(flavor: Channel<F>) {
+        loom::model(move || {
+            let (tx, mut rx) = flavor();
+            let t = thread::spawn(move || tx.close());
+            assert_eq!(rx.recv(), None);
+            t.join().unwrap();
+        });
+    }
+    model(channel);
+    model(list_channel);
+    model(block_channel);
+}
+
+#[test]
+fn bounded_receiver_drop_wakes_sender() {
+    loom::model(|| {
+        let (mut tx, rx) = sync_channel(1);
+        tx.send(1).unwrap();
+        let t = thread::spawn(move || drop(rx));
+        // Blocks on the full queue until the receiver leaves
+        assert!(tx.send(2).is_err());
+        t.join().unwrap();
+    });
+}
+
+#[test]
+fn rendezvous() {
+    loom::model(|| {
+        let (mut tx, mut rx) = sync_channel(0);
+        let t = thread::spawn(move || tx.send(42));
+        assert_eq!(rx.recv(), Some(42));
+        assert!(t.join().unwrap().is_ok());
+    });
+}
+
+#[test]
+fn oneshot_send() {
+    loom::model(|| {
+        let (tx, rx) = oneshot();
+        let t = thread::spawn(move || tx.send(42).unwrap());
+        assert_eq!(rx.recv(), Ok(42));
+        t.join().unwrap();
+    });
+}